@@ -0,0 +1,31 @@
+// Fixtures shared by the benchmarks. Each benchmark uses only some 
+// of them.
+#![allow(dead_code)]
+
+// len bytes of log-like text with some variation, from a fixed 
+// xorshift seed.
+pub fn log_text(len: usize) -> Vec<u8> {
+    let mut x: u32 = 2463534242;
+    let mut data = Vec::with_capacity(len + 100);
+    while data.len() < len {
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        let line = format!("2021-06-{:02} 12:{:02}:{:02} INFO request {} served in {}ms\n",
+            x % 30 + 1, (x >> 5) % 60, (x >> 11) % 60, x % 1000, (x >> 17) % 500);
+        data.extend_from_slice(line.as_bytes());
+    }
+    data.truncate(len);
+    data
+}
+
+// len uniformly random bytes, from a fixed xorshift seed.
+pub fn random(len: usize) -> Vec<u8> {
+    let mut x: u64 = 0x9E3779B97F4A7C15;
+    (0..len).map(|_| {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        x as u8
+    }).collect()
+}
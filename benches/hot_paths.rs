@@ -0,0 +1,145 @@
+// Measures the bits per second of each predictor's p/update pair, and of
+// the coder alone with a fixed probability, over fixed synthetic input.
+// Each benchmark is warmed up, then timed over several samples with a
+// fresh predictor or coder, reporting the median and the fastest and
+// slowest sample so that changes can be compared run to run.
+// cargo bench --bench hot_paths [-- filter]
+use std::{
+    env,
+    hint::black_box,
+    time::{Duration, Instant},
+};
+use fpaq0::{
+    header::Precision,
+    models::{Fpaq0, Fpaq0p, Fpaq0f, Fpaq0fApm, Mix, Model, OrderN},
+    Decoder, Encoder, Predictor,
+};
+
+mod common;
+
+const SAMPLE_LEN: usize = 1 << 18;
+const SAMPLES: usize = 10;
+
+fn bits(data: &[u8]) -> Vec<i32> {
+    data.iter().flat_map(|&byte| (0..8).rev().map(move |i| ((byte >> i) & 1) as i32)).collect()
+}
+
+// Predicts every bit with the same probability, so that only the
+// coder is measured. Streams are headed as model with its defaults, 
+// which decides how a Standard coder splits its range.
+struct Fixed {
+    p:      u32,
+    model:  Model,
+}
+impl Predictor for Fixed {
+    fn p(&mut self) -> u32 {
+        self.p
+    }
+    fn p16(&mut self) -> u32 {
+        self.p << 4
+    }
+    fn update(&mut self, _bit: i32) {}
+    fn model(&self) -> Model {
+        self.model
+    }
+    fn params(&self) -> Vec<u8> {
+        self.model.predictor().params()
+    }
+}
+
+struct Bench {
+    filter:  Option<String>,
+}
+impl Bench {
+    // Times f over SAMPLES samples after a warm up, each coding bits
+    // bits, with setup run untimed before each sample.
+    fn run<S, T>(&self, name: &str, bits: usize, mut setup: impl FnMut() -> S, mut f: impl FnMut(S) -> T) {
+        if self.filter.as_ref().is_some_and(|filter| !name.contains(filter.as_str())) {
+            return;
+        }
+        black_box(f(setup()));
+        let mut times: Vec<Duration> = (0..SAMPLES).map(|_| {
+            let state = setup();
+            let start_time = Instant::now();
+            black_box(f(state));
+            start_time.elapsed()
+        }).collect();
+        times.sort();
+        let mbits_per_s = |time: Duration| bits as f64 / time.as_secs_f64() / 1e6;
+        println!("{:<40}{:>12.2}{:>12.2}{:>12.2}", name,
+            mbits_per_s(times[SAMPLES / 2]), mbits_per_s(times[SAMPLES - 1]), mbits_per_s(times[0]));
+    }
+    fn predictor<P: Predictor>(&self, name: &str, new: impl Fn() -> P, input: &str, bits: &[i32]) {
+        self.run(&format!("{}/p/{}", name, input), bits.len(), &new, |mut predictor| {
+            let mut sum = 0;
+            for &bit in bits {
+                sum = predictor.p().wrapping_add(sum);
+                predictor.update(bit);
+            }
+            sum
+        });
+        self.run(&format!("{}/p16/{}", name, input), bits.len(), &new, |mut predictor| {
+            let mut sum = 0;
+            for &bit in bits {
+                sum = predictor.p16().wrapping_add(sum);
+                predictor.update(bit);
+            }
+            sum
+        });
+    }
+    // The coder with the range split of model at precision.
+    fn coder(&self, p: u32, precision: Precision, model: Model, input: &str, bits: &[i32]) {
+        let len = bits.len() as u64 / 8;
+        let encode = |bits: &[i32]| {
+            let mut enc = Encoder::with_precision(Fixed { p, model }, Vec::new(), Some(len), precision).unwrap();
+            for &bit in bits {
+                enc.encode(bit).unwrap();
+            }
+            enc.flush().unwrap();
+            enc.into_inner()
+        };
+        let name = format!("coder/{}/{}/p={}/{}", precision.name(), model.name(), p, input);
+        self.run(&format!("{}/encode", name), bits.len(), || (), |_| encode(bits));
+
+        let stream = encode(bits);
+        self.run(&format!("{}/decode", name), bits.len(), || (), |_| {
+            let mut dec = Decoder::new(Fixed { p, model }, &stream[..]).unwrap();
+            let mut sum = 0;
+            for _ in 0..bits.len() {
+                sum += dec.decode().unwrap();
+            }
+            sum
+        });
+    }
+}
+
+fn main() {
+    let bench = Bench {
+        filter:  env::args().skip(1).find(|arg| !arg.starts_with("--")),
+    };
+    let inputs = [("text", bits(&common::log_text(SAMPLE_LEN))), ("random", bits(&common::random(SAMPLE_LEN)))];
+    println!("{} bits per input, Mbit/s", SAMPLE_LEN * 8);
+    println!("{:<40}{:>12}{:>12}{:>12}", "benchmark", "median", "slowest", "fastest");
+    for (input, bits) in &inputs {
+        bench.predictor("fpaq0",      Fpaq0::new,         input, bits);
+        bench.predictor("fpaq0p",     Fpaq0p::new,        input, bits);
+        bench.predictor("fpaq0f",     Fpaq0f::new,        input, bits);
+        bench.predictor("fpaq0f-apm", Fpaq0fApm::new,     input, bits);
+        bench.predictor("order1",     || OrderN::new(1),  input, bits);
+        bench.predictor("order2",     || OrderN::new(2),  input, bits);
+        bench.predictor("mix",        Mix::new,           input, bits);
+    }
+    for (input, bits) in &inputs {
+        // fpaq0 splits a Standard range coarsely, fpaq0f in full.
+        let splits = [
+            (Precision::Standard, Model::Fpaq0),
+            (Precision::Standard, Model::Fpaq0f),
+            (Precision::High,     Model::Fpaq0f),
+        ];
+        for (precision, model) in splits {
+            for p in [2048, 4000] {
+                bench.coder(p, precision, model, input, bits);
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+// Compares the throughput of coding one bit at a time through 4096 byte
+// buffered readers and writers with the byte fast path and bulk I/O, 
+// then the byte fast path of the models of the original programs with 
+// their coding loop.
+// cargo bench --bench throughput [-- file]
+use std::{
+    env, fs,
+    hint::black_box,
+    io::{BufReader, BufWriter, Write},
+    time::{Duration, Instant},
+};
+use fpaq0::{
+    buffer::next_byte,
+    header::Precision,
+    models::{Model, WithPredictor},
+    predictor::Predictor,
+    compress_stream, decompress_legacy, decompress_stream, Decoder, Encoder,
+};
+
+mod common;
+
+const SAMPLE_LEN: usize = 1 << 20;
+
+fn compress_bitwise(model: Model, data: &[u8]) -> Vec<u8> {
+    let mut file_in = BufReader::with_capacity(4096, data);
+    let file_out = BufWriter::with_capacity(4096, Vec::new());
+    let mut enc = Encoder::new(model.predictor(), file_out, data.len() as u64).unwrap();
+    while let Some(byte) = next_byte(&mut file_in).unwrap() {
+        for i in (0..=7).rev() {
+            enc.encode(((byte >> i) & 1).into()).unwrap();
+        }
+    }
+    enc.flush().unwrap();
+    enc.into_inner().into_inner().unwrap()
+}
+fn decompress_bitwise(model: Model, stream: &[u8]) -> Vec<u8> {
+    let mut dec = Decoder::new(model.predictor(), BufReader::with_capacity(4096, stream)).unwrap();
+    let mut file_out = BufWriter::with_capacity(4096, Vec::new());
+    for _ in 0..dec.header().len.unwrap() {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = (byte << 1) | dec.decode().unwrap() as u8;
+        }
+        file_out.write_all(&[byte]).unwrap();
+    }
+    file_out.into_inner().unwrap()
+}
+fn compress_bytes(model: Model, data: &[u8]) -> Vec<u8> {
+    compress_stream(model.predictor(), &mut &data[..], Vec::new(), Some(data.len() as u64), Precision::Standard).unwrap()
+}
+fn decompress_bytes(model: Model, stream: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    decompress_stream(model.predictor(), stream, &mut data, None).unwrap();
+    data
+}
+
+// The coding loop of the original programs, headerless with a flag 
+// before each byte and the end of the data, through 4096 byte buffers.
+fn compress_baseline<P: Predictor>(mut predictor: P, data: &[u8]) -> Vec<u8> {
+    let coarse = predictor.model().coarse_mid();
+    let flag = predictor.flag().unwrap();
+    let (mut high, mut low) = (0xFFFFFFFFu32, 0u32);
+    let mut file_in = BufReader::with_capacity(4096, data);
+    let mut file_out = BufWriter::with_capacity(4096, Vec::new());
+    let mut encode = |bit: i32, file_out: &mut BufWriter<Vec<u8>>| {
+        let p = predictor.p();
+        let mid = baseline_mid(high, low, p, coarse);
+        if bit == 1 { 
+            high = mid;    
+        } 
+        else {        
+            low = mid + 1; 
+        }
+        predictor.update(bit);
+        while ((high ^ low) & 0xFF000000) == 0 {
+            file_out.write_all(&[(high >> 24) as u8]).unwrap();
+            high = (high << 8) + 255;
+            low <<= 8;  
+        }
+    };
+    while let Some(byte) = next_byte(&mut file_in).unwrap() {
+        encode(flag, &mut file_out);
+        for i in (0..=7).rev() {
+            encode(((byte >> i) & 1).into(), &mut file_out);
+        }
+    }
+    encode(flag ^ 1, &mut file_out);
+    file_out.write_all(&[(high >> 24) as u8]).unwrap();
+    file_out.into_inner().unwrap()
+}
+fn decompress_baseline<P: Predictor>(mut predictor: P, stream: &[u8]) -> Vec<u8> {
+    let coarse = predictor.model().coarse_mid();
+    let flag = predictor.flag().unwrap();
+    let (mut high, mut low, mut x) = (0xFFFFFFFFu32, 0u32, 0u32);
+    let mut file_in = BufReader::with_capacity(4096, stream);
+    let mut file_out = BufWriter::with_capacity(4096, Vec::new());
+    for _ in 0..4 {
+        x = (x << 8) + next_byte(&mut file_in).unwrap().unwrap_or(0) as u32;
+    }
+    let mut decode = || {
+        let mut bit = 0;
+        let mid = baseline_mid(high, low, predictor.p(), coarse);
+        if x <= mid {
+            bit = 1;
+            high = mid;
+        } 
+        else {
+            low = mid + 1;
+        }
+        predictor.update(bit);
+        while ((high ^ low) & 0xFF000000) == 0 {
+            high = (high << 8) + 255;
+            low <<= 8;
+            x = (x << 8) + next_byte(&mut file_in).unwrap().unwrap_or(0) as u32; 
+        }
+        bit
+    };
+    while decode() == flag {
+        let mut byte: u32 = 1;
+        while byte < 256 {
+            byte = byte * 2 + decode() as u32;
+        }
+        file_out.write_all(&[byte as u8]).unwrap();
+    }
+    file_out.into_inner().unwrap()
+}
+fn baseline_mid(high: u32, low: u32, p: u32, coarse: bool) -> u32 {
+    if coarse {
+        low + ((high - low) >> 12) * p
+    }
+    else {
+        low + ((high - low) >> 12) * p + ((((high - low) & 0x0FFF) * p) >> 12)
+    }
+}
+
+// Times the original coding loop and the byte fast path with the 
+// predictor of the original program.
+struct Baseline<'a> {
+    data: &'a [u8],
+}
+impl WithPredictor for Baseline<'_> {
+    type Output = [Duration; 4];
+
+    fn run<P: Predictor + Clone + Send + Sync + 'static>(self, predictor: P) -> [Duration; 4] {
+        let data = self.data;
+        let (stream, enc_base) = time(|| compress_baseline(predictor.clone(), data));
+        let mut decoded = Vec::new();
+        decompress_legacy(predictor.clone(), &stream[..], &mut decoded, None).unwrap();
+        assert!(decoded == data);
+        let (decoded, dec_base) = time(|| decompress_baseline(predictor.clone(), &stream));
+        assert!(decoded == data);
+
+        let (stream, enc_lib) = time(|| compress_stream(predictor.clone(), &mut &data[..], 
+            Vec::new(), Some(data.len() as u64), Precision::Standard).unwrap());
+        let (decoded, dec_lib) = time(|| {
+            let mut decoded = Vec::new();
+            decompress_stream(predictor.clone(), &stream[..], &mut decoded, None).unwrap();
+            decoded
+        });
+        assert!(decoded == data);
+        [enc_base, enc_lib, dec_base, dec_lib]
+    }
+}
+
+const RUNS: usize = 11;
+
+// Median of RUNS runs, so that one slow or fast run does not decide 
+// the gain.
+fn time<T>(mut f: impl FnMut() -> T) -> (T, Duration) {
+    let mut result = None;
+    let mut times: Vec<Duration> = (0..RUNS).map(|_| {
+        let start_time = Instant::now();
+        result = Some(black_box(f()));
+        start_time.elapsed()
+    }).collect();
+    times.sort();
+    (result.unwrap(), times[RUNS / 2])
+}
+fn mb_per_s(len: usize, time: Duration) -> f64 {
+    len as f64 / time.as_secs_f64() / 1e6
+}
+
+fn main() {
+    let data = match env::args().skip(1).find(|arg| !arg.starts_with("--")) {
+        Some(path) => fs::read(path).expect("cannot read input"),
+        None       => common::log_text(SAMPLE_LEN),
+    };
+    println!("{} bytes, median MB/s of uncompressed data over {} runs", data.len(), RUNS);
+    println!("{:<12}{:>12}{:>12}{:>9}{:>12}{:>12}{:>9}",
+        "model", "enc bits", "enc bytes", "gain", "dec bits", "dec bytes", "gain");
+    for model in Model::ALL {
+        let (stream, enc_bits) = time(|| compress_bitwise(model, &data));
+        let (fast_stream, enc_bytes) = time(|| compress_bytes(model, &data));
+        assert!(stream == fast_stream);
+        let (decoded, dec_bits) = time(|| decompress_bitwise(model, &stream));
+        assert!(decoded == data);
+        let (decoded, dec_bytes) = time(|| decompress_bytes(model, &stream));
+        assert!(decoded == data);
+
+        println!("{:<12}{:>12.2}{:>12.2}{:>8.2}x{:>12.2}{:>12.2}{:>8.2}x", model.name(),
+            mb_per_s(data.len(), enc_bits), mb_per_s(data.len(), enc_bytes),
+            enc_bits.as_secs_f64() / enc_bytes.as_secs_f64(),
+            mb_per_s(data.len(), dec_bits), mb_per_s(data.len(), dec_bytes),
+            dec_bits.as_secs_f64() / dec_bytes.as_secs_f64());
+    }
+
+    println!();
+    println!("{:<12}{:>12}{:>12}{:>9}{:>12}{:>12}{:>9}",
+        "original", "enc loop", "enc bytes", "gain", "dec loop", "dec bytes", "gain");
+    for model in Model::ALL {
+        let Some([enc_base, enc_lib, dec_base, dec_lib]) = model.with_legacy(Baseline { data: &data }) else {
+            continue;
+        };
+        println!("{:<12}{:>12.2}{:>12.2}{:>8.2}x{:>12.2}{:>12.2}{:>8.2}x", model.name(),
+            mb_per_s(data.len(), enc_base), mb_per_s(data.len(), enc_lib),
+            enc_base.as_secs_f64() / enc_lib.as_secs_f64(),
+            mb_per_s(data.len(), dec_base), mb_per_s(data.len(), dec_lib),
+            dec_base.as_secs_f64() / dec_lib.as_secs_f64());
+    }
+}
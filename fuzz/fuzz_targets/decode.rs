@@ -0,0 +1,13 @@
+#![no_main]
+// Decodes arbitrary input as a stream of any model, which must end 
+// with data or an error. Output is limited, as garbage can decode to 
+// confident predictions that cost almost no input.
+use std::io;
+use libfuzzer_sys::fuzz_target;
+use fpaq0::decompress_any;
+
+const MAX_OUTPUT: u64 = 1 << 16;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decompress_any(data, &mut io::sink(), Some(MAX_OUTPUT));
+});
@@ -0,0 +1,18 @@
+#![no_main]
+// Decodes arbitrary input as a framed container, and reads its index 
+// and the second half of the data it claims as a seekable one.
+use std::io::{self, Cursor};
+use libfuzzer_sys::fuzz_target;
+use fpaq0::framed::{decompress_framed, decompress_range, read_index};
+
+const MAX_OUTPUT: u64 = 1 << 16;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decompress_framed(&mut &data[..], &mut io::sink(), 2, Some(MAX_OUTPUT));
+    let Ok((index, len)) = read_index(&mut Cursor::new(data)) else {
+        return;
+    };
+    assert!(index.len() <= data.len() / 16);
+    let offset = len / 2;
+    let _ = decompress_range(&mut Cursor::new(data), offset, len - offset, Some(MAX_OUTPUT));
+});
@@ -0,0 +1,54 @@
+use std::iter::repeat_n;
+use crate::logistic::{squash, Stretch};
+
+// Adaptive Probability Map ----------------------------------------
+#[derive(Clone)]
+pub struct Apm {
+    s:         Stretch,
+    bin:       usize,    
+    num_cxts:  usize, 
+    bin_map:   Vec<u16>, // maps each bin to a squashed value
+}
+impl Apm {
+    pub fn new(n: usize) -> Apm {
+        Apm {
+            s:         Stretch::new(),
+            bin:       0,
+            num_cxts:  n,
+            bin_map:   repeat_n(
+                       (0..33).map(|i| (squash((i - 16) * 128) * 16) as u16)
+                       .collect::<Vec<u16>>(), n)
+                       .flatten()
+                       .collect::<Vec<u16>>(),
+        }
+    }
+    // Refines pr (12 bits) in context cxt, returning a probability with 
+    // 16 bits (0..65536), after updating the last prediction with bit.
+    #[inline(always)]
+    pub fn p16(&mut self, bit: i32, rate: i32, mut pr: i32, cxt: usize) -> i32 {
+        assert!(bit == 0 || (bit == 1 && (0..4096).contains(&pr) && cxt < self.num_cxts));
+        self.update(bit, rate);
+        
+        pr = self.s.stretch(pr); // -2047 to 2047
+        let i_w = pr & 127; // Interpolation weight (33 points)
+        
+        self.bin = (((pr + 2048) >> 7) + ((cxt as i32) * 33)) as usize;
+
+        let a = self.bin_map[self.bin] as i32;
+        let b = self.bin_map[self.bin+1] as i32;
+        ((a * (128 - i_w)) + (b * i_w)) >> 7
+    }
+    #[inline(always)]
+    fn update(&mut self, bit: i32, rate: i32) {
+        assert!(bit == 0 || (bit == 1 && rate > 0 && rate < 32));
+        
+        // Controls direction of update (bit = 1 - increase, bit = 0 - decrease)
+        let g: i32 = (bit << 16) + (bit << rate) - bit - bit;
+
+        let a = self.bin_map[self.bin] as i32;
+        let b = self.bin_map[self.bin+1] as i32;
+        self.bin_map[self.bin]   = (a + ((g - a) >> rate)) as u16;
+        self.bin_map[self.bin+1] = (b + ((g - b) >> rate)) as u16;
+    }
+}
+// -----------------------------------------------------------------
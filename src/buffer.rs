@@ -0,0 +1,73 @@
+use std::{
+    fs::File,
+    io::{self, Read, Write, BufReader, BufWriter, ErrorKind},
+};
+
+// Size of the buffers used for bulk reads and writes.
+pub const BUF_SIZE: usize = 1 << 16;
+
+// Convenience functions for buffered I/O ---------------------------
+// Reads a single byte, returning None at the end of the input.
+pub fn next_byte<R: Read>(input: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0; 1];
+    loop {
+        match input.read(&mut byte) {
+            Ok(0)  => return Ok(None),
+            Ok(_)  => return Ok(Some(byte[0])),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {},
+            Err(e) => return Err(e),
+        }
+    }
+}
+// Reads up to buf.len() bytes, returning 0 at the end of the input.
+pub fn read_some<R: Read>(input: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    loop {
+        match input.read(buf) {
+            Err(e) if e.kind() == ErrorKind::Interrupted => {},
+            result => return result,
+        }
+    }
+}
+pub fn new_input_file(capacity: usize, file_name: &str) -> io::Result<BufReader<File>> {
+    Ok(BufReader::with_capacity(capacity, File::open(file_name)?))
+}
+pub fn new_output_file(capacity: usize, file_name: &str) -> io::Result<BufWriter<File>> {
+    Ok(BufWriter::with_capacity(capacity, File::create(file_name)?))
+}
+// ------------------------------------------------------------------
+
+// Counter ----------------------------------------------------------
+// Counts the bytes passing through a reader or writer.
+pub struct Counter<T> {
+    inner:  T,
+    count:  u64,
+}
+impl<T> Counter<T> {
+    pub fn new(inner: T) -> Counter<T> {
+        Counter { inner, count: 0 }
+    }
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+impl<R: Read> Read for Counter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+impl<W: Write> Write for Counter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+// ------------------------------------------------------------------
@@ -0,0 +1,59 @@
+// CRC-32 (IEEE) ----------------------------------------------------
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+#[derive(Clone, Copy)]
+pub struct Crc32 {
+    crc: u32,
+}
+impl Crc32 {
+    pub fn new() -> Crc32 {
+        Crc32 { crc: 0xFFFFFFFF }
+    }
+    #[inline]
+    pub fn update(&mut self, byte: u8) {
+        self.crc = CRC_TABLE[((self.crc ^ byte as u32) & 0xFF) as usize] ^ (self.crc >> 8);
+    }
+    pub fn update_slice(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.update(byte);
+        }
+    }
+    pub fn value(&self) -> u32 {
+        self.crc ^ 0xFFFFFFFF
+    }
+}
+impl Default for Crc32 {
+    fn default() -> Crc32 {
+        Crc32::new()
+    }
+}
+// ------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::Crc32;
+
+    #[test]
+    fn check_value() {
+        let mut crc = Crc32::new();
+        crc.update_slice(b"123456789");
+        assert_eq!(crc.value(), 0xCBF43926);
+        assert_eq!(Crc32::new().value(), 0);
+    }
+}
@@ -0,0 +1,574 @@
+use std::io::{Read, Write};
+use crate::{
+    buffer::{read_some, BUF_SIZE},
+    checksum::Crc32,
+    error::{Error, HeaderError, Result},
+    header::{Header, Mode, Precision, LEGACY_VERSION},
+    models::Model,
+    predictor::Predictor,
+};
+
+// Probability that another byte follows (12 bits), for models that 
+// don't predict flags themselves.
+const FLAG_P: u32 = 4095;
+
+// Headerless streams were flushed with a single byte, so decoding them 
+// legitimately shifts up to 3 bytes past the end of the input into x.
+// Any more than MAX_SLACK bytes past the end means the input was truncated.
+const MAX_SLACK: u32 = 4;
+
+// Range ------------------------------------------------------------
+// The coder's range is kept in u64s for both precisions. A Standard 
+// range only ever uses the low 32 bits, and codes exactly as the 
+// original 32 bit coders did.
+//
+// How the range is split is a type implementing Split, so the coding 
+// loops are compiled for each split instead of choosing one for every 
+// bit. Range records which split a stream uses, and split! calls 
+// generic code with its type.
+macro_rules! split {
+    ($range:expr, $S:ident => $body:expr) => {
+        match $range {
+            Range::Standard => { type $S = Standard; $body }
+            Range::Coarse   => { type $S = Coarse; $body }
+            Range::High     => { type $S = High; $body }
+        }
+    };
+}
+#[derive(Clone, Copy)]
+enum Range {
+    Standard,
+    Coarse,   // Standard, ignoring the low 12 bits of the range in mid
+    High,
+}
+impl Range {
+    fn new(precision: Precision, model: Model) -> Range {
+        match precision {
+            Precision::Standard if model.coarse_mid() => Range::Coarse,
+            Precision::Standard => Range::Standard,
+            Precision::High     => Range::High,
+        }
+    }
+    fn mask(self) -> u64 {
+        split!(self, S => S::MASK)
+    }
+    fn bytes(self) -> usize {
+        split!(self, S => S::BYTES)
+    }
+}
+
+trait Split {
+    const P_BITS: u32;  // Bits of the probabilities coded
+    const COARSE: bool; // Ignore the low P_BITS of the range in mid
+    const BYTES:  usize = if Self::P_BITS == 12 { 4 } else { 8 };
+    const TOP:    u32 = Self::BYTES as u32 * 8 - 8; // Shift of the top byte of the range
+    const MASK:   u64 = u64::MAX >> (64 - Self::BYTES * 8); // All bits of the range
+    const FLAG_P: u32 = FLAG_P << (Self::P_BITS - 12);
+
+    fn predict<P: Predictor>(predictor: &mut P) -> u32;
+    // The point dividing low..=high in proportion to p, the probability
+    // of a 1 (which is coded below and including mid).
+    #[inline(always)]
+    fn mid(high: u64, low: u64, p: u32) -> u64 {
+        let range = high - low;
+        let mid = low + (range >> Self::P_BITS) * p as u64;
+        if Self::COARSE {
+            return mid;
+        }
+        mid + (((range & ((1 << Self::P_BITS) - 1)) * p as u64) >> Self::P_BITS)
+    }
+}
+struct Standard;
+struct Coarse;
+struct High;
+impl Split for Standard {
+    const P_BITS: u32 = 12;
+    const COARSE: bool = false;
+    #[inline(always)]
+    fn predict<P: Predictor>(predictor: &mut P) -> u32 {
+        predictor.p()
+    }
+}
+impl Split for Coarse {
+    const P_BITS: u32 = 12;
+    const COARSE: bool = true;
+    #[inline(always)]
+    fn predict<P: Predictor>(predictor: &mut P) -> u32 {
+        predictor.p()
+    }
+}
+impl Split for High {
+    const P_BITS: u32 = 16;
+    const COARSE: bool = false;
+    #[inline(always)]
+    fn predict<P: Predictor>(predictor: &mut P) -> u32 {
+        predictor.p16()
+    }
+}
+// ------------------------------------------------------------------
+
+
+// Encoder ----------------------------------------------------------
+// Coded bytes are collected in buf and written to file_out BUF_SIZE 
+// at a time, so file_out doesn't need to be buffered.
+pub struct Encoder<W: Write, P: Predictor> {
+    predictor:  P,
+    mode:       Mode,
+    len:        Option<u64>, // Length in the header, in Length mode
+    count:      u64,         // Bytes encoded
+    c0:         u32,         // Bits of the current byte, with a leading 1
+    crc:        Crc32,       // Checksum of the original data
+    range:      Range,
+    high:       u64,
+    low:        u64,
+    buf:        Vec<u8>,     // Coded bytes not yet written to file_out
+    file_out:   W,
+}
+impl<W: Write, P: Predictor> Encoder<W, P> {
+    // Writes a header recording the model and the original length (in bytes).
+    // Exactly len bytes must then be encoded, or flush() fails.
+    pub fn new(predictor: P, file_out: W, len: u64) -> Result<Encoder<W, P>> {
+        Encoder::with_precision(predictor, file_out, Some(len), Precision::Standard)
+    }
+    // For input of unknown length, marks the end of the stream with flag bits.
+    pub fn new_flagged(predictor: P, file_out: W) -> Result<Encoder<W, P>> {
+        Encoder::with_precision(predictor, file_out, None, Precision::Standard)
+    }
+    // As new() if len is known and new_flagged() if not, with the 
+    // coder precision recorded in the header.
+    pub fn with_precision(predictor: P, mut file_out: W, len: Option<u64>, 
+                          precision: Precision) -> Result<Encoder<W, P>> {
+        let mode = if len.is_some() { Mode::Length } else { Mode::Flagged };
+        Header::new(predictor.model(), predictor.params(), mode, precision, len).write(&mut file_out)?;
+        let range = Range::new(precision, predictor.model());
+        Ok(Encoder {
+            predictor, 
+            mode,
+            len,
+            count: 0,
+            c0: 1,
+            crc: Crc32::new(),
+            range,
+            high: range.mask(), 
+            low: 0,  
+            buf: Vec::with_capacity(BUF_SIZE + 8),
+            file_out,
+        })
+    }
+    pub fn encode_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        split!(self.range, S => {
+            for &byte in bytes {
+                self.encode_byte_with::<S>(byte)?;
+            }
+            Ok(())
+        })
+    }
+    pub fn encode_byte(&mut self, byte: u8) -> Result<()> {
+        split!(self.range, S => self.encode_byte_with::<S>(byte))
+    }
+    pub fn encode(&mut self, bit: i32) -> Result<()> {
+        split!(self.range, S => self.encode_bit::<S>(bit))
+    }
+    #[inline(always)]
+    fn encode_byte_with<S: Split>(&mut self, byte: u8) -> Result<()> {
+        if self.c0 != 1 {
+            // Not at a byte boundary, after calls to encode()
+            for i in (0..=7).rev() {
+                self.encode_bit::<S>(((byte >> i) & 1).into())?;
+            }
+            return Ok(());
+        }
+        self.encode_flag::<S>(true);
+        for i in (0..=7).rev() {
+            let bit = ((byte >> i) & 1) as i32;
+            let p = S::predict(&mut self.predictor);
+            self.code::<S>(bit, p);
+            self.predictor.update(bit);
+        }
+        self.crc.update(byte);
+        self.count += 1;
+        self.write_buf(BUF_SIZE)
+    }
+    fn encode_bit<S: Split>(&mut self, bit: i32) -> Result<()> {
+        if self.c0 == 1 {
+            self.encode_flag::<S>(true);
+        }
+        let p = S::predict(&mut self.predictor);
+        self.code::<S>(bit, p);
+        self.predictor.update(bit);
+
+        self.c0 = self.c0 * 2 + bit as u32;
+        if self.c0 >= 256 {
+            self.crc.update((self.c0 - 256) as u8);
+            self.count += 1;
+            self.c0 = 1;
+        }
+        self.write_buf(BUF_SIZE)
+    }
+    // Marks whether another byte follows. Flags are only coded in 
+    // Flagged mode, but models that predict them are updated with them 
+    // in both modes (see Predictor).
+    #[inline(always)]
+    fn encode_flag<S: Split>(&mut self, more: bool) {
+        match self.predictor.flag() {
+            Some(flag) => {
+                // Predictors are always asked for p before update.
+                let bit = if more { flag } else { flag ^ 1 };
+                let p = S::predict(&mut self.predictor);
+                if self.mode == Mode::Flagged {
+                    self.code::<S>(bit, p);
+                }
+                self.predictor.update(bit);
+            }
+            None if self.mode == Mode::Flagged => self.code::<S>(more as i32, S::FLAG_P),
+            None => {}
+        }
+    }
+    // Writes buf to file_out once it holds at least min bytes.
+    fn write_buf(&mut self, min: usize) -> Result<()> {
+        if self.buf.len() >= min {
+            self.file_out.write_all(&self.buf)?;
+            self.buf.clear();
+        }
+        Ok(())
+    }
+    #[inline(always)]
+    fn code<S: Split>(&mut self, bit: i32, p: u32) {
+        let mid = S::mid(self.high, self.low, p);
+        if bit == 1 { 
+            self.high = mid;    
+        } 
+        else {        
+            self.low = mid + 1; 
+        }
+        self.shift_out::<S>();
+    }
+    // Writes the leading bytes of the range once high and low agree on them.
+    #[inline(always)]
+    fn shift_out<S: Split>(&mut self) {
+        while ((self.high ^ self.low) >> S::TOP) == 0 {
+            self.buf.push((self.high >> S::TOP) as u8);
+            self.high = ((self.high << 8) & S::MASK) | 255;
+            self.low = (self.low << 8) & S::MASK;  
+        }
+    }
+    // Ends the stream, writing the end of stream flag in Flagged mode 
+    // and the checksum of the original data.
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some(len) = self.len.filter(|&len| len != self.count) {
+            return Err(Error::LengthMismatch { expected: len, found: self.count });
+        }
+        split!(self.range, S => {
+            if self.mode == Mode::Flagged {
+                self.encode_flag::<S>(false);
+            }
+            self.shift_out::<S>();
+        });
+        self.buf.extend_from_slice(&self.high.to_be_bytes()[8 - self.range.bytes()..]);
+        self.buf.extend_from_slice(&self.crc.value().to_le_bytes());
+        self.write_buf(0)?;
+        self.file_out.flush()?;
+        Ok(())
+    }
+    // Writes the coded bytes so far to file_out and flushes it, without 
+    // ending the stream. The last few bytes are held by the coder 
+    // until flush().
+    pub fn flush_output(&mut self) -> Result<()> {
+        self.write_buf(0)?;
+        self.file_out.flush()?;
+        Ok(())
+    }
+    // file_out doesn't include coded bytes still held in the buffer.
+    pub fn get_ref(&self) -> &W {
+        &self.file_out
+    }
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.file_out
+    }
+    pub fn into_inner(self) -> W {
+        self.file_out
+    }
+}
+// ------------------------------------------------------------------
+
+
+// Decoder ----------------------------------------------------------
+// Coded bytes are read from file_in BUF_SIZE at a time, so file_in 
+// doesn't need to be buffered, but the decoder may read past the end 
+// of the stream.
+pub struct Decoder<R: Read, P: Predictor> {
+    predictor:  P,
+    header:     Header,
+    remaining:  u64,
+    decoded:    u64,
+    max_output: Option<u64>,
+    finished:   bool,
+    slack:      u32,     // Bytes read past the end of file_in
+    c0:         u32,
+    crc:        Crc32,
+    range:      Range,
+    high:       u64,
+    low:        u64,
+    x:          u64,
+    buf:        Vec<u8>, // Bytes read from file_in
+    pos:        usize,   // Position of the next byte in buf
+    file_in:    R,   
+}
+impl<R: Read, P: Predictor> Decoder<R, P> {
+    // Fails if the header is invalid or the stream was produced by 
+    // a different model, or the same model with different parameters.
+    pub fn new(predictor: P, mut file_in: R) -> Result<Decoder<R, P>> {
+        let header = Header::read(&mut file_in)?;
+        Decoder::with_header(predictor, file_in, header)
+    }
+    // For the headerless streams of the original programs, which 
+    // predictor must be the model of (see Model::legacy_predictor).
+    pub fn legacy(predictor: P, file_in: R) -> Result<Decoder<R, P>> {
+        if predictor.flag().is_none() {
+            return Err(HeaderError::NotLegacy { model: predictor.model() }.into());
+        }
+        let header = Header::legacy(predictor.model(), predictor.params());
+        Decoder::with_header(predictor, file_in, header)
+    }
+    // For when the header has already been read from file_in.
+    pub fn with_header(predictor: P, file_in: R, header: Header) -> Result<Decoder<R, P>> {
+        if header.model != predictor.model() {
+            return Err(HeaderError::WrongModel { 
+                expected: predictor.model(), 
+                found:    header.model, 
+            }.into());
+        }
+        if header.params != predictor.params() {
+            return Err(HeaderError::WrongParams { model: header.model }.into());
+        }
+        let range = Range::new(header.precision, header.model);
+        let mut dec = Decoder {
+            predictor, 
+            remaining: header.len.unwrap_or(0),
+            decoded: 0,
+            max_output: None,
+            finished: false,
+            slack: 0,
+            c0: 1,
+            crc: Crc32::new(),
+            header,
+            range,
+            high: range.mask(), 
+            low: 0, 
+            x: 0, 
+            buf: Vec::with_capacity(BUF_SIZE),
+            pos: 0,
+            file_in, 
+        };
+        for _ in 0..range.bytes() {
+            let byte = dec.next_coded_byte()?;
+            dec.x = (dec.x << 8) | byte as u64;
+        }
+        Ok(dec)
+    }
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+    // Fails with OutputLimit rather than decode more than max bytes. 
+    // Length streams stop at the length in the header anyway, and fail 
+    // before decoding anything if it is more than max.
+    pub fn set_max_output(&mut self, max: u64) {
+        self.max_output = Some(max);
+    }
+    // file_in may have been read past the end of the stream.
+    pub fn into_inner(self) -> R {
+        self.file_in
+    }
+    // Fills bytes, returning how many were decoded. This is less than 
+    // bytes.len() only at the end of the stream.
+    pub fn decode_bytes(&mut self, bytes: &mut [u8]) -> Result<usize> {
+        split!(self.range, S => {
+            for (n, byte) in bytes.iter_mut().enumerate() {
+                match self.decode_byte_with::<S>()? {
+                    Some(b) => *byte = b,
+                    None    => return Ok(n),
+                }
+            }
+            Ok(bytes.len())
+        })
+    }
+    // Returns None once the end of the stream is reached, 
+    // after checking the checksum of the decoded data.
+    pub fn decode_byte(&mut self) -> Result<Option<u8>> {
+        split!(self.range, S => self.decode_byte_with::<S>())
+    }
+    // Fails with Truncated past the end of the stream.
+    pub fn decode(&mut self) -> Result<i32> {
+        split!(self.range, S => self.decode_bit::<S>())
+    }
+    #[inline(always)]
+    fn decode_byte_with<S: Split>(&mut self) -> Result<Option<u8>> {
+        if self.finished {
+            return Ok(None);
+        }
+        if self.c0 != 1 {
+            // Not at a byte boundary, after calls to decode()
+            let mut dec_byte: i32 = 1;
+            while dec_byte < 256 {
+                dec_byte = dec_byte * 2 + self.decode_bit::<S>()?;
+            }
+            return Ok(Some((dec_byte - 256) as u8));
+        }
+        if !self.start_byte::<S>()? {
+            return Ok(None);
+        }
+        let byte = self.decode_bits::<S>()?;
+        self.crc.update(byte);
+        Ok(Some(byte))
+    }
+    // The 8 bits of a byte, as code() with the coder's state in locals. 
+    // update() is called in each branch so that the bit stays a predicted
+    // branch: as a conditional move, the table lookups of the next 
+    // prediction would wait for this one.
+    #[inline(always)]
+    fn decode_bits<S: Split>(&mut self) -> Result<u8> {
+        let (mut high, mut low, mut x) = (self.high, self.low, self.x);
+        let mut dec_byte: u32 = 1;
+        while dec_byte < 256 {
+            let mid = S::mid(high, low, S::predict(&mut self.predictor));
+            if x <= mid {
+                high = mid;
+                self.predictor.update(1);
+                dec_byte = dec_byte * 2 + 1;
+            } 
+            else {
+                low = mid + 1;
+                self.predictor.update(0);
+                dec_byte *= 2;
+            }
+            while ((high ^ low) >> S::TOP) == 0 {
+                high = ((high << 8) & S::MASK) | 255;
+                low = (low << 8) & S::MASK;
+                let byte = self.next_coded_byte()?; 
+                x = ((x << 8) & S::MASK) | byte as u64; 
+            }
+        }
+        (self.high, self.low, self.x) = (high, low, x);
+        Ok((dec_byte - 256) as u8)
+    }
+    fn decode_bit<S: Split>(&mut self) -> Result<i32> {
+        if self.finished || (self.c0 == 1 && !self.start_byte::<S>()?) {
+            return Err(Error::Truncated);
+        }
+        let p = S::predict(&mut self.predictor);
+        let bit = self.code::<S>(p)?;
+        self.predictor.update(bit);
+
+        self.c0 = self.c0 * 2 + bit as u32;
+        if self.c0 >= 256 {
+            self.crc.update((self.c0 - 256) as u8);
+            self.c0 = 1;
+        }
+        Ok(bit)
+    }
+    // Decodes the flag before a byte, or counts it against the length, 
+    // returning false at the end of the stream.
+    #[inline(always)]
+    fn start_byte<S: Split>(&mut self) -> Result<bool> {
+        let more = match self.header.mode {
+            Mode::Flagged => self.decode_flag::<S>()?,
+            Mode::Length if self.remaining == 0 => false,
+            Mode::Length => {
+                self.remaining -= 1;
+                self.decode_flag::<S>()?
+            }
+        };
+        if !more {
+            self.finish()?;
+            return Ok(false);
+        }
+        if let Some(max) = self.max_output {
+            if self.decoded >= max || self.header.len.is_some_and(|len| len > max) {
+                return Err(Error::OutputLimit { limit: max });
+            }
+        }
+        self.decoded += 1;
+        Ok(true)
+    }
+    fn finish(&mut self) -> Result<()> {
+        self.finished = true;
+        if self.header.version == LEGACY_VERSION {
+            return Ok(());
+        }
+        let mut expected = [0; 4];
+        for byte in expected.iter_mut() {
+            *byte = self.next_input_byte()?.ok_or(Error::Truncated)?;
+        }
+        let expected = u32::from_le_bytes(expected);
+        let found = self.crc.value();
+        if expected != found {
+            return Err(Error::ChecksumMismatch { expected, found });
+        }
+        Ok(())
+    }
+    // As Encoder::encode_flag, returning whether another byte follows.
+    #[inline(always)]
+    fn decode_flag<S: Split>(&mut self) -> Result<bool> {
+        match self.predictor.flag() {
+            Some(flag) => {
+                let p = S::predict(&mut self.predictor);
+                let bit = match self.header.mode {
+                    Mode::Flagged => self.code::<S>(p)?,
+                    Mode::Length  => flag,
+                };
+                self.predictor.update(bit);
+                Ok(bit == flag)
+            }
+            None if self.header.mode == Mode::Flagged => Ok(self.code::<S>(S::FLAG_P)? == 1),
+            None => Ok(true),
+        }
+    }
+    #[inline(always)]
+    fn code<S: Split>(&mut self, p: u32) -> Result<i32> {
+        let mut bit: i32 = 0;
+        let mid = S::mid(self.high, self.low, p);
+        if self.x <= mid {
+            bit = 1;
+            self.high = mid;
+        } 
+        else {
+            self.low = mid + 1;
+        }
+        
+        while ((self.high ^ self.low) >> S::TOP) == 0 {
+            self.high = ((self.high << 8) & S::MASK) | 255;
+            self.low = (self.low << 8) & S::MASK;
+            let byte = self.next_coded_byte()?; 
+            self.x = ((self.x << 8) & S::MASK) | byte as u64; 
+        }
+        Ok(bit)
+    }
+    // Reads the next byte of coded data, padding the end of the input with 
+    // zeros up to MAX_SLACK bytes.
+    fn next_coded_byte(&mut self) -> Result<u8> {
+        match self.next_input_byte()? {
+            Some(byte) => Ok(byte),
+            None => {
+                self.slack += 1;
+                if self.slack > MAX_SLACK {
+                    return Err(Error::Truncated);
+                }
+                Ok(0)
+            }
+        }
+    }
+    fn next_input_byte(&mut self) -> Result<Option<u8>> {
+        if self.pos == self.buf.len() {
+            self.buf.resize(BUF_SIZE, 0);
+            let n = read_some(&mut self.file_in, &mut self.buf)?;
+            self.buf.truncate(n);
+            self.pos = 0;
+            if n == 0 {
+                return Ok(None);
+            }
+        }
+        self.pos += 1;
+        Ok(Some(self.buf[self.pos - 1]))
+    }
+}
+// ------------------------------------------------------------------
@@ -0,0 +1,135 @@
+use std::{fmt, io};
+use crate::models::Model;
+
+// Errors -----------------------------------------------------------
+#[derive(Debug)]
+pub enum HeaderError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    UnknownModel(u8),
+    UnknownMode(u8),
+    UnknownPrecision(u8),
+    UnknownLength,
+    WrongModel { expected: Model, found: Model },
+    WrongParams { model: Model },
+    BadParams { model: Model },
+    NotSeekable,
+    BadBlockSize(u32),
+    BadIndex,
+    NotLegacy { model: Model },
+}
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeaderError::BadMagic => 
+                write!(f, "not an fpaq0 stream"),
+            HeaderError::UnsupportedVersion(v) => 
+                write!(f, "unsupported format version {}", v),
+            HeaderError::UnknownModel(id) => 
+                write!(f, "unknown model id {}", id),
+            HeaderError::UnknownMode(m) => 
+                write!(f, "unknown mode {}", m),
+            HeaderError::UnknownPrecision(p) => 
+                write!(f, "unknown coder precision {}", p),
+            HeaderError::UnknownLength => 
+                write!(f, "length mode stream has no length"),
+            HeaderError::WrongModel { expected, found } => 
+                write!(f, "stream was compressed with {}, not {}", found, expected),
+            HeaderError::WrongParams { model } => 
+                write!(f, "stream was compressed with different {} parameters", model),
+            HeaderError::BadParams { model } => 
+                write!(f, "invalid {} parameters", model),
+            HeaderError::NotSeekable => 
+                write!(f, "container has no index"),
+            HeaderError::BadBlockSize(size) => 
+                write!(f, "invalid block size {}", size),
+            HeaderError::BadIndex => 
+                write!(f, "container index does not match its blocks"),
+            HeaderError::NotLegacy { model } => 
+                write!(f, "{} predictor cannot decode headerless streams", model),
+        }
+    }
+}
+
+// A model parameter chosen at compression time that the model does 
+// not have or that is outside of its legal range.
+#[derive(Debug)]
+pub enum ParamError {
+    OutOfRange { name: &'static str, value: u32, min: u32, max: u32 },
+    NotApplicable { name: &'static str, model: Model },
+    BadApmChain,
+}
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamError::OutOfRange { name, value, min, max } => 
+                write!(f, "{} must be from {} to {}, not {}", name, min, max, value),
+            ParamError::NotApplicable { name, model } => 
+                write!(f, "{} has no {} parameter", model, name),
+            ParamError::BadApmChain => 
+                write!(f, "APM chains have at most 16 stages and 2^21 contexts, and the first stage cannot be parallel"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Truncated,
+    BadHeader(HeaderError),
+    ChecksumMismatch { expected: u32, found: u32 },
+    InvalidParam(ParamError),
+    OutputLimit { limit: u64 },
+    LengthMismatch { expected: u64, found: u64 },
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Truncated => write!(f, "compressed input is truncated"),
+            Error::BadHeader(e) => write!(f, "bad header: {}", e),
+            Error::ChecksumMismatch { expected, found } => 
+                write!(f, "checksum mismatch (expected {:08x}, found {:08x})", expected, found),
+            Error::InvalidParam(e) => write!(f, "invalid parameter: {}", e),
+            Error::OutputLimit { limit } => 
+                write!(f, "decompressed data is larger than the limit of {} bytes", limit),
+            Error::LengthMismatch { expected, found } => 
+                write!(f, "{} bytes were encoded, not the {} in the header", found, expected),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+impl From<Error> for io::Error {
+    fn from(e: Error) -> io::Error {
+        match e {
+            Error::Io(e) => e,
+            Error::Truncated => io::Error::new(io::ErrorKind::UnexpectedEof, e),
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+impl From<HeaderError> for Error {
+    fn from(e: HeaderError) -> Error {
+        Error::BadHeader(e)
+    }
+}
+impl From<ParamError> for Error {
+    fn from(e: ParamError) -> Error {
+        Error::InvalidParam(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+// ------------------------------------------------------------------
@@ -0,0 +1,353 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Read, Seek, SeekFrom, Write},
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Mutex},
+    thread,
+};
+use crate::{
+    buffer::{next_byte, Counter, BUF_SIZE},
+    coder::Decoder,
+    error::{Error, HeaderError, Result},
+    header::{Header, Precision},
+    predictor::Predictor,
+    compress_stream, decompress_any,
+};
+
+pub const MAGIC: [u8; 4] = *b"fpqF";
+pub const INDEX_MAGIC: [u8; 4] = *b"fpqI";
+pub const VERSION: u8 = 1;
+pub const SEEKABLE_VERSION: u8 = 2;
+pub const MAX_BLOCK_SIZE: u32 = 1 << 30;
+
+const HEADER_LEN: u64 = 9;
+const FOOTER_LEN: u64 = 20;
+
+// Framed Container -------------------------------------------------
+// The input is split into blocks that are compressed independently,
+// so they can be coded in parallel:
+// magic (4) | version (1) | block size (4, LE) | frames | 0 (4)
+// Each frame is the length of a stream (4, LE) followed by the stream
+// of one block, with its own header and checksum.
+//
+// Seekable containers (version 2) end with an index of the blocks, so
+// a range of the data can be decoded without reading the other blocks:
+// ... | 0 (4) | index | number of blocks (8, LE) | length (8, LE) | "fpqI"
+// where the index holds the uncompressed offset (8, LE) and compressed 
+// offset (8, LE, from the start of the container) of every frame.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Container {
+    pub seekable:    bool,
+    pub block_size:  u32,
+}
+
+// Blocks are coded by threads workers while the next blocks are read 
+// and the coded ones written. Up to 2 * threads blocks are in flight, 
+// so memory use is about 4 * threads * block size.
+pub fn compress_framed<F, P, R, W>(new_predictor: F, file_in: &mut R, file_out: W,
+                                   block_size: u32, threads: usize, precision: Precision) -> Result<W>
+where F: Fn() -> P + Sync, P: Predictor, R: Read, W: Write {
+    compress_blocks(new_predictor, file_in, file_out, block_size, threads, precision, false)
+}
+// As compress_framed, but ends the container with an index of the blocks.
+pub fn compress_seekable<F, P, R, W>(new_predictor: F, file_in: &mut R, file_out: W,
+                                     block_size: u32, threads: usize, precision: Precision) -> Result<W>
+where F: Fn() -> P + Sync, P: Predictor, R: Read, W: Write {
+    compress_blocks(new_predictor, file_in, file_out, block_size, threads, precision, true)
+}
+fn compress_blocks<F, P, R, W>(new_predictor: F, file_in: &mut R, file_out: W, block_size: u32, 
+                               threads: usize, precision: Precision, seekable: bool) -> Result<W>
+where F: Fn() -> P + Sync, P: Predictor, R: Read, W: Write {
+    assert!(block_size > 0 && block_size <= MAX_BLOCK_SIZE && threads > 0);
+    let mut file_out = Counter::new(file_out);
+    let mut index = Vec::new(); // (uncompressed offset, compressed offset) of each block
+    let mut len = 0;
+    file_out.write_all(&MAGIC)?;
+    file_out.write_all(&[if seekable { SEEKABLE_VERSION } else { VERSION }])?;
+    file_out.write_all(&block_size.to_le_bytes())?;
+    let next_block = || {
+        let mut block = Vec::new();
+        file_in.by_ref().take(block_size as u64).read_to_end(&mut block)?;
+        Ok((!block.is_empty()).then_some(block))
+    };
+    let compress_block = |block: Vec<u8>| {
+        let file_out = Vec::with_capacity(block.len() / 2 + 64);
+        let stream = compress_stream(new_predictor(), &mut &block[..], file_out, Some(block.len() as u64), precision)?;
+        Ok((block.len(), stream))
+    };
+    pipeline(threads, next_block, compress_block, |(block_len, stream)| {
+        index.push((len, file_out.count()));
+        len += block_len as u64;
+        file_out.write_all(&(stream.len() as u32).to_le_bytes())?;
+        file_out.write_all(&stream)?;
+        Ok(())
+    })?;
+    file_out.write_all(&0u32.to_le_bytes())?;
+    if seekable {
+        for (offset, compressed_offset) in &index {
+            file_out.write_all(&offset.to_le_bytes())?;
+            file_out.write_all(&compressed_offset.to_le_bytes())?;
+        }
+        file_out.write_all(&(index.len() as u64).to_le_bytes())?;
+        file_out.write_all(&len.to_le_bytes())?;
+        file_out.write_all(&INDEX_MAGIC)?;
+    }
+    file_out.flush()?;
+    Ok(file_out.into_inner())
+}
+// Decompresses a framed container, using the model in the header of
+// each block. Fails if the container or any block is invalid, or with 
+// Error::OutputLimit rather than write more than max_output bytes. 
+// No block decodes to more than the block size either way.
+pub fn decompress_framed<R, W>(file_in: &mut R, file_out: &mut W, threads: usize, 
+                               max_output: Option<u64>) -> Result<()>
+where R: Read, W: Write {
+    assert!(threads > 0);
+    let container = read_header(file_in)?;
+    let max_block = max_output.map_or(container.block_size as u64, |max| max.min(container.block_size as u64));
+    let mut len = 0;
+    let next_stream = || {
+        let len = read_u32(file_in)?;
+        if len == 0 {
+            return Ok(None);
+        }
+        let mut stream = Vec::new();
+        file_in.by_ref().take(len as u64).read_to_end(&mut stream)?;
+        if stream.len() != len as usize {
+            return Err(Error::Truncated);
+        }
+        Ok(Some(stream))
+    };
+    let decompress_block = |stream: Vec<u8>| {
+        let mut block = Vec::new();
+        decompress_any(&stream[..], &mut block, Some(max_block))?;
+        Ok(block)
+    };
+    pipeline(threads, next_stream, decompress_block, |block| {
+        len += block.len() as u64;
+        if let Some(max) = max_output.filter(|&max| len > max) {
+            return Err(Error::OutputLimit { limit: max });
+        }
+        file_out.write_all(&block)?;
+        Ok(())
+    })?;
+    file_out.flush()?;
+    Ok(())
+}
+// Decodes len bytes starting at offset from a seekable container, 
+// reading only the blocks that cover them. Each block is decoded only 
+// up to the end of the range, so its checksum is checked only if the 
+// range covers its end. Fails with Error::OutputLimit rather than 
+// decode more than max_output bytes, counting those of the first block
+// before offset.
+pub fn decompress_range<R: Read + Seek>(file_in: &mut R, offset: u64, len: u64, 
+                                        max_output: Option<u64>) -> Result<Vec<u8>> {
+    let (_, index, total_len, frames_end) = read_container(file_in)?;
+    let end = offset.checked_add(len).filter(|&end| end <= total_len).ok_or_else(|| 
+        io::Error::new(io::ErrorKind::InvalidInput, "range is past the end of the data"))?;
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+
+    // Not allocated up front, as len comes from the index.
+    let mut data = Vec::new();
+    let mut decoded = 0;
+    let mut buf = Vec::new();
+    let first = index.partition_point(|&(block_offset, _)| block_offset <= offset).saturating_sub(1);
+    for (i, &(block_offset, compressed_offset)) in index.iter().enumerate().skip(first) {
+        if block_offset >= end {
+            break;
+        }
+        let (block_end, frame_end) = index.get(i + 1).copied().unwrap_or((total_len, frames_end));
+        file_in.seek(SeekFrom::Start(compressed_offset))?;
+        let stream_len = read_u32(file_in)?;
+        if compressed_offset + 4 + stream_len as u64 != frame_end {
+            return Err(HeaderError::BadIndex.into());
+        }
+        let mut stream = file_in.by_ref().take(stream_len as u64);
+        let header = Header::read(&mut stream)?;
+        let block_len = block_end - block_offset;
+        if header.len.is_some_and(|len| len != block_len) {
+            return Err(HeaderError::BadIndex.into());
+        }
+        let predictor = header.model.predictor_with_params(&header.params)
+            .ok_or(HeaderError::BadParams { model: header.model })?;
+        let mut dec = Decoder::with_header(predictor, stream, header)?;
+        dec.set_max_output(block_len);
+
+        let from = offset.saturating_sub(block_offset);
+        let to = end.min(block_end) - block_offset;
+        let mut pos = 0;
+        while pos < to {
+            let n = (to - pos).min(BUF_SIZE as u64);
+            decoded += n;
+            if let Some(max) = max_output.filter(|&max| decoded > max) {
+                return Err(Error::OutputLimit { limit: max });
+            }
+            buf.resize(n as usize, 0);
+            if dec.decode_bytes(&mut buf)? < buf.len() {
+                return Err(HeaderError::BadIndex.into());
+            }
+            let start = from.saturating_sub(pos).min(n) as usize;
+            data.extend_from_slice(&buf[start..]);
+            pos += n;
+        }
+        if to == block_len && dec.decode_byte()?.is_some() {
+            return Err(HeaderError::BadIndex.into());
+        }
+    }
+    if data.len() as u64 != len {
+        return Err(Error::Truncated);
+    }
+    Ok(data)
+}
+// The (uncompressed offset, compressed offset) of each block.
+pub type Index = Vec<(u64, u64)>;
+
+// Reads the index at the end of a seekable container, returning it 
+// and the length of the data.
+pub fn read_index<R: Read + Seek>(file_in: &mut R) -> Result<(Index, u64)> {
+    let (_, index, len, _) = read_container(file_in)?;
+    Ok((index, len))
+}
+// Also returns where the frames end, so that the index can be checked
+// against the container: blocks follow each other, every block but the
+// last is full, and the frames lie between the header and the end.
+fn read_container<R: Read + Seek>(file_in: &mut R) -> Result<(Container, Index, u64, u64)> {
+    file_in.seek(SeekFrom::Start(0))?;
+    let container = read_header(file_in)?;
+    if !container.seekable {
+        return Err(HeaderError::NotSeekable.into());
+    }
+    let file_len = file_in.seek(SeekFrom::End(0))?;
+    if file_len < HEADER_LEN + 4 + FOOTER_LEN {
+        return Err(Error::Truncated);
+    }
+    file_in.seek(SeekFrom::End(-(FOOTER_LEN as i64)))?;
+    let blocks = read_u64(file_in)?;
+    let len = read_u64(file_in)?;
+    let mut magic = [0; 4];
+    for byte in magic.iter_mut() {
+        *byte = read_u8(file_in)?;
+    }
+    if magic != INDEX_MAGIC {
+        return Err(Error::Truncated);
+    }
+    let index_len = blocks.checked_mul(16)
+        .filter(|&index_len| index_len <= file_len - HEADER_LEN - 4 - FOOTER_LEN)
+        .ok_or(Error::Truncated)?;
+    let frames_end = file_len - FOOTER_LEN - index_len - 4;
+    file_in.seek(SeekFrom::Start(frames_end + 4))?;
+    let block_size = container.block_size as u64;
+    let mut index: Index = Vec::with_capacity(blocks as usize);
+    for i in 0..blocks {
+        let entry = (read_u64(file_in)?, read_u64(file_in)?);
+        if entry.0 != i * block_size || entry.1 < index.last().map_or(HEADER_LEN, |last| last.1 + 4) {
+            return Err(HeaderError::BadIndex.into());
+        }
+        index.push(entry);
+    }
+    let last_len = len.checked_sub(index.last().map_or(0, |last| last.0));
+    let last_frame_end = index.last().map_or(HEADER_LEN, |last| last.1 + 4);
+    if last_len.is_none_or(|last_len| last_len > block_size || (last_len == 0) != (blocks == 0))
+        || last_frame_end > frames_end {
+        return Err(HeaderError::BadIndex.into());
+    }
+    Ok((container, index, len, frames_end))
+}
+// Reads the container header.
+pub fn read_header<R: Read>(file_in: &mut R) -> Result<Container> {
+    let mut magic = [0; 4];
+    for byte in magic.iter_mut() {
+        *byte = read_u8(file_in)?;
+    }
+    if magic != MAGIC {
+        return Err(HeaderError::BadMagic.into());
+    }
+    let version = read_u8(file_in)?;
+    if version != VERSION && version != SEEKABLE_VERSION {
+        return Err(HeaderError::UnsupportedVersion(version).into());
+    }
+    let block_size = read_u32(file_in)?;
+    if block_size == 0 || block_size > MAX_BLOCK_SIZE {
+        return Err(HeaderError::BadBlockSize(block_size).into());
+    }
+    Ok(Container {
+        seekable:    version == SEEKABLE_VERSION,
+        block_size,
+    })
+}
+// Runs f on every item from next on threads workers, passing the 
+// results to write in the order of the items. The workers take items 
+// from a channel, so items are read and results written while others 
+// are coded, with at most 2 * threads items read but not yet written.
+// Stops at the first error, and re-raises a panic of f.
+fn pipeline<T, U, N, F, W>(threads: usize, mut next: N, f: F, mut write: W) -> Result<()>
+where T: Send, U: Send, N: FnMut() -> Result<Option<T>>, F: Fn(T) -> Result<U> + Sync, 
+      W: FnMut(U) -> Result<()> {
+    let f = &f;
+    let (items, item_rx) = mpsc::channel::<(usize, T)>();
+    let (result_tx, results) = mpsc::channel();
+    let item_rx = &Mutex::new(item_rx);
+    // Moves items into the scope, so that returning from it closes the 
+    // channel and the workers exit before the scope waits for them.
+    thread::scope(move |s| {
+        for _ in 0..threads {
+            let result_tx = result_tx.clone();
+            s.spawn(move || loop {
+                // The lock is only held while waiting for an item.
+                let Ok((i, item)) = item_rx.lock().unwrap().recv() else {
+                    break;
+                };
+                let result = panic::catch_unwind(AssertUnwindSafe(|| f(item)));
+                if result_tx.send((i, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(result_tx);
+
+        // Results that arrived before those of earlier items.
+        let mut done = BTreeMap::new();
+        let (mut read, mut written) = (0, 0);
+        let mut finished = false;
+        loop {
+            while !finished && read - written < 2 * threads {
+                match next()? {
+                    Some(item) => {
+                        items.send((read, item)).expect("coding threads exited");
+                        read += 1;
+                    }
+                    None => finished = true,
+                }
+            }
+            if written == read {
+                return Ok(());
+            }
+            let (i, result) = results.recv().expect("coding threads exited");
+            done.insert(i, result.unwrap_or_else(|payload| panic::resume_unwind(payload))?);
+            while let Some(result) = done.remove(&written) {
+                write(result)?;
+                written += 1;
+            }
+        }
+    })
+}
+fn read_u8<R: Read>(file_in: &mut R) -> Result<u8> {
+    next_byte(file_in)?.ok_or(Error::Truncated)
+}
+pub fn read_u32<R: Read>(file_in: &mut R) -> Result<u32> {
+    let mut bytes = [0; 4];
+    for byte in bytes.iter_mut() {
+        *byte = read_u8(file_in)?;
+    }
+    Ok(u32::from_le_bytes(bytes))
+}
+fn read_u64<R: Read>(file_in: &mut R) -> Result<u64> {
+    let mut bytes = [0; 8];
+    for byte in bytes.iter_mut() {
+        *byte = read_u8(file_in)?;
+    }
+    Ok(u64::from_le_bytes(bytes))
+}
+// ------------------------------------------------------------------
@@ -0,0 +1,154 @@
+use std::io::{self, Read, Write};
+use crate::{
+    buffer::next_byte,
+    error::{Error, HeaderError, Result},
+    models::Model,
+};
+
+pub const MAGIC: [u8; 4] = *b"fpq0";
+pub const VERSION: u8 = 1;
+pub const LEGACY_VERSION: u8 = 0; // Headerless streams of the original programs
+
+const UNKNOWN_LEN: u64 = u64::MAX;
+
+// Mode -------------------------------------------------------------
+// How the end of the stream is found. Flagged streams code an extra 
+// flag bit before every byte and after the last one, Length streams 
+// code exactly 8 bits per byte and stop after the length in the header.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Flagged,
+    Length,
+}
+// ------------------------------------------------------------------
+
+// Precision --------------------------------------------------------
+// The arithmetic coder a stream was coded with. Standard coders keep 
+// a 32 bit range and code 12 bit probabilities (Predictor::p), High 
+// coders a 64 bit range and 16 bit probabilities (Predictor::p16), so 
+// confident predictions cost fewer bits.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Precision {
+    Standard,
+    High,
+}
+impl Precision {
+    pub fn name(self) -> &'static str {
+        match self {
+            Precision::Standard => "standard",
+            Precision::High     => "high",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Precision> {
+        match name {
+            "standard" => Some(Precision::Standard),
+            "high"     => Some(Precision::High),
+            _ => None,
+        }
+    }
+}
+// ------------------------------------------------------------------
+
+// Header -----------------------------------------------------------
+// magic (4) | version (1) | model (1) | mode (1) | precision (1) | params length (1) | params | original length (8, LE)
+//
+// The coded data is followed by a CRC-32 of the original data (4, LE), 
+// and is flushed with all bytes of the coder's range so the checksum 
+// starts exactly where the decoder stops reading.
+//
+// The original fpaq0, fpaq0p, fpaq0f and fpaq0f-apm programs wrote no 
+// header at all. Their streams are Flagged, with no checksum, and the 
+// coded data flushed with a single byte; Header::legacy describes them 
+// as version 0.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Header {
+    pub version:    u8,
+    pub model:      Model,
+    pub params:     Vec<u8>,
+    pub mode:       Mode,
+    pub precision:  Precision,
+    pub len:        Option<u64>,
+}
+impl Header {
+    pub fn new(model: Model, params: Vec<u8>, mode: Mode, precision: Precision, len: Option<u64>) -> Header {
+        assert!(params.len() < 256);
+        assert!(mode == Mode::Flagged || len.is_some());
+        Header { version: VERSION, model, params, mode, precision, len }
+    }
+    // A headerless stream of the original program of model.
+    pub fn legacy(model: Model, params: Vec<u8>) -> Header {
+        Header { 
+            version:    LEGACY_VERSION, 
+            model, 
+            params, 
+            mode:       Mode::Flagged, 
+            precision:  Precision::Standard, 
+            len:        None,
+        }
+    }
+    pub fn write<W: Write>(&self, file_out: &mut W) -> io::Result<()> {
+        assert!(self.version != LEGACY_VERSION);
+        let mut header = Vec::with_capacity(16 + self.params.len());
+        header.extend_from_slice(&MAGIC);
+        header.push(self.version);
+        header.push(self.model.id());
+        header.push(match self.mode {
+            Mode::Flagged => 0,
+            Mode::Length  => 1,
+        });
+        header.push(match self.precision {
+            Precision::Standard => 0,
+            Precision::High     => 1,
+        });
+        header.push(self.params.len() as u8);
+        header.extend_from_slice(&self.params);
+        header.extend_from_slice(&self.len.unwrap_or(UNKNOWN_LEN).to_le_bytes());
+        file_out.write_all(&header)
+    }
+    pub fn read<R: Read>(file_in: &mut R) -> Result<Header> {
+        let mut magic = [0; 4];
+        for byte in magic.iter_mut() {
+            *byte = read_u8(file_in)?;
+        }
+        if magic != MAGIC {
+            return Err(HeaderError::BadMagic.into());
+        }
+        let version = read_u8(file_in)?;
+        if version != VERSION {
+            return Err(HeaderError::UnsupportedVersion(version).into());
+        }
+        let id = read_u8(file_in)?;
+        let model = Model::from_id(id).ok_or(HeaderError::UnknownModel(id))?;
+
+        let mode = match read_u8(file_in)? {
+            0 => Mode::Flagged,
+            1 => Mode::Length,
+            m => return Err(HeaderError::UnknownMode(m).into()),
+        };
+        let precision = match read_u8(file_in)? {
+            0 => Precision::Standard,
+            1 => Precision::High,
+            p => return Err(HeaderError::UnknownPrecision(p).into()),
+        };
+
+        let params_len = read_u8(file_in)? as usize;
+        let mut params = Vec::with_capacity(params_len);
+        for _ in 0..params_len {
+            params.push(read_u8(file_in)?);
+        }
+        let mut len = [0; 8];
+        for byte in len.iter_mut() {
+            *byte = read_u8(file_in)?;
+        }
+        let len = match u64::from_le_bytes(len) {
+            UNKNOWN_LEN if mode == Mode::Flagged => None,
+            UNKNOWN_LEN => return Err(HeaderError::UnknownLength.into()),
+            len => Some(len),
+        };
+        Ok(Header { version, model, params, mode, precision, len })
+    }
+}
+fn read_u8<R: Read>(file_in: &mut R) -> Result<u8> {
+    next_byte(file_in)?.ok_or(Error::Truncated)
+}
+// ------------------------------------------------------------------
@@ -0,0 +1,113 @@
+pub mod buffer;
+pub mod checksum;
+pub mod coder;
+pub mod error;
+pub mod framed;
+pub mod header;
+pub mod models;
+pub mod predictor;
+pub mod stream;
+pub mod tune;
+
+mod apm;
+mod logistic;
+mod match_model;
+mod mixer;
+mod state_table;
+mod statemap;
+
+use std::io::{Read, Write};
+use crate::{
+    buffer::{read_some, BUF_SIZE},
+    error::HeaderError,
+    models::WithPredictor,
+};
+
+pub use crate::{
+    coder::{Encoder, Decoder},
+    error::{Error, Result},
+    header::{Header, Mode, Precision},
+    predictor::Predictor,
+    stream::{CompressWriter, DecompressReader},
+};
+
+// Drivers ----------------------------------------------------------
+// Compresses all of file_in. If the length of the input is known it is 
+// recorded in the header (Length mode), otherwise the stream is Flagged.
+pub fn compress_stream<P, R, W>(predictor: P, file_in: &mut R, file_out: W, len: Option<u64>, 
+                                precision: Precision) -> Result<W> 
+where P: Predictor, R: Read, W: Write {
+    let mut enc = Encoder::with_precision(predictor, file_out, len, precision)?;
+    let mut buf = vec![0; BUF_SIZE];
+    loop {
+        let n = read_some(file_in, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        enc.encode_bytes(&buf[..n])?;
+    }
+    enc.flush()?;
+    Ok(enc.into_inner())
+}
+// Fails with Error::OutputLimit rather than write more than max_output 
+// bytes to file_out. With no limit, Length streams are still limited 
+// to the length in their header, but Flagged streams are not.
+pub fn decompress_stream<P, R, W>(predictor: P, file_in: R, file_out: &mut W, max_output: Option<u64>) -> Result<()> 
+where P: Predictor, R: Read, W: Write {
+    decode_all(Decoder::new(predictor, file_in)?, file_out, max_output)
+}
+// Decompresses a headerless stream of the original programs.
+pub fn decompress_legacy<P, R, W>(predictor: P, file_in: R, file_out: &mut W, max_output: Option<u64>) -> Result<()> 
+where P: Predictor, R: Read, W: Write {
+    decode_all(Decoder::legacy(predictor, file_in)?, file_out, max_output)
+}
+// Decompresses a stream produced by any of the models, 
+// using the model and parameters recorded in its header.
+pub fn decompress_any<R: Read, W: Write>(mut file_in: R, file_out: &mut W, max_output: Option<u64>) -> Result<Header> {
+    let header = Header::read(&mut file_in)?;
+    let decode = DecodeAll { file_in, file_out, header: header.clone(), max_output };
+    header.model.with_params(&header.params, decode)
+        .ok_or(HeaderError::BadParams { model: header.model })??;
+    Ok(header)
+}
+struct DecodeAll<'a, R, W> {
+    file_in:    R,
+    file_out:   &'a mut W,
+    header:     Header,
+    max_output: Option<u64>,
+}
+impl<R: Read, W: Write> WithPredictor for DecodeAll<'_, R, W> {
+    type Output = Result<()>;
+    fn run<P: Predictor + Clone + Send + Sync + 'static>(self, predictor: P) -> Result<()> {
+        decode_all(Decoder::with_header(predictor, self.file_in, self.header)?, self.file_out, self.max_output)
+    }
+}
+fn decode_all<R, P, W>(mut dec: Decoder<R, P>, file_out: &mut W, max_output: Option<u64>) -> Result<()> 
+where R: Read, P: Predictor, W: Write {
+    if let Some(max) = max_output {
+        dec.set_max_output(max);
+    }
+    let mut buf = vec![0; BUF_SIZE];
+    loop {
+        let n = dec.decode_bytes(&mut buf)?;
+        file_out.write_all(&buf[..n])?;
+        if n < buf.len() {
+            break;
+        }
+    }
+    file_out.flush()?;
+    Ok(())
+}
+
+pub fn compress<P: Predictor>(predictor: P, data: &[u8]) -> Vec<u8> {
+    let mut file_in = data;
+    let file_out = Vec::with_capacity(data.len() / 2 + 32);
+    compress_stream(predictor, &mut file_in, file_out, Some(data.len() as u64), Precision::Standard)
+        .expect("compressing into memory cannot fail")
+}
+pub fn decompress<P: Predictor>(predictor: P, data: &[u8]) -> Result<Vec<u8>> {
+    let mut file_out = Vec::with_capacity(data.len() * 2);
+    decompress_stream(predictor, data, &mut file_out, None)?;
+    Ok(file_out)
+}
+// ------------------------------------------------------------------
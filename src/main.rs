@@ -0,0 +1,710 @@
+use std::{
+    env,
+    fs::File,
+    io::{self, Read, Write, BufReader, BufWriter},
+    process,
+    thread,
+    time::{Duration, Instant},
+};
+use fpaq0::{
+    buffer::{Counter, BUF_SIZE},
+    header::{Mode, Precision},
+    framed::{self, compress_framed, compress_seekable, decompress_framed, decompress_range},
+    models::{ApmStage, Model, Tuning, WithPredictor},
+    compress_stream, decompress_any, decompress_legacy, Header, Predictor, Result,
+};
+
+const USAGE: &str = "\
+Usage: fpaq <command> [options] [input] [output]
+       fpaq bench [options] [input...]
+
+Commands:
+    compress     Compress input to output
+    decompress   Decompress input to output, using the model in its header
+    test         Decompress input, discarding the output
+    info         Print the header of compressed input
+    bench        Compress and decompress each input in memory with each 
+                 model, printing the compressed size, bits per byte, speed 
+                 and peak memory (if built with the bench-memory feature)
+    tune         Search for the model parameters that compress a sample of
+                 input best, and compress input to output with them if an 
+                 output is given
+
+Options:
+    -m, --model <name>   fpaq0, fpaq0p, fpaq0f, fpaq0f-apm, order1, order2 
+                         or mix (default fpaq0f)
+    --halve-at <n>       fpaq0 count halving limit (2 to 524288, default 65534)
+    --shift <n>          fpaq0p update rate, 1/2^n of the error (1 to 15, 
+                         default 5)
+    --init <p>           fpaq0p initial 16 bit probability (1 to 65535, 
+                         default 32768)
+    --limit <n>          StateMap count limit of fpaq0f, fpaq0f-apm, order1,
+                         order2 and mix, higher adapts slower (1 to 511, 
+                         default 127)
+    --apm <stages>       fpaq0f-apm APM chain, a comma separated list of 
+                         context:bits:rate:weight[:p] stages, where context
+                         is order0, order1, byte2low5, hash16 or hash24, 
+                         and :p blends a stage in parallel with the previous
+                         (default order0:8:5:4,order0:8:9:2:p,order1:16:7:4,
+                         byte2low5:13:7:3,hash24:14:7:2)
+    -p, --precision <p>  Coder precision, standard (32 bit range, 12 bit 
+                         probabilities) or high (64 bit range, 16 bit 
+                         probabilities) (default standard)
+    --sample <bytes>     Size of the sample searched by tune (default 262144)
+    -b, --block-size <bytes>
+                         Compress independent blocks of this size (with an
+                         optional K, M or G suffix) in parallel into a framed
+                         container
+    --seekable           With --block-size, end the container with an index of
+                         the blocks so that ranges can be decompressed
+    --offset <bytes>     Decompress only from this offset of a seekable 
+                         container (default 0)
+    --length <bytes>     Decompress only this many bytes of a seekable 
+                         container (default to the end)
+    --max-output <bytes> Fail rather than decompress more than this many bytes
+                         (default the length in the header, if it has one)
+    --legacy             Decompress a headerless stream of the original
+                         fpaq0, fpaq0p, fpaq0f or fpaq0f-apm program, given
+                         by --model (default fpaq0f)
+    -t, --threads <n>    Threads compressing or decompressing blocks 
+                         (default the number of CPUs)
+    --csv                Print bench results as CSV instead of a table
+    -h, --help           Print this message
+
+A missing or '-' input or output reads stdin or writes stdout.";
+
+const DEFAULT_MODEL: Model = Model::Fpaq0f;
+const DEFAULT_SAMPLE: usize = 1 << 18;
+
+// Command Line -----------------------------------------------------
+#[derive(PartialEq, Eq)]
+enum Command {
+    Compress,
+    Decompress,
+    Test,
+    Info,
+    Bench,
+    Tune,
+}
+
+struct Options {
+    command:     Command,
+    model:       Option<Model>,
+    tuning:      Tuning,
+    precision:   Precision,
+    sample:      usize,
+    block_size:  Option<u32>,
+    seekable:    bool,
+    range:       Option<(u64, Option<u64>)>, // Offset and length to decompress
+    max_output:  Option<u64>,
+    legacy:      bool,
+    threads:     usize,
+    csv:         bool,
+    input:       String,
+    output:      Option<String>,
+    inputs:      Vec<String>,     // Every input of bench
+}
+impl Options {
+    fn parse(args: &[String]) -> std::result::Result<Options, String> {
+        let command = match args.first().map(String::as_str) {
+            Some("compress"   | "c") => Command::Compress,
+            Some("decompress" | "d") => Command::Decompress,
+            Some("test")  => Command::Test,
+            Some("info")  => Command::Info,
+            Some("bench") => Command::Bench,
+            Some("tune")  => Command::Tune,
+            Some(cmd) => return Err(format!("unknown command '{}'", cmd)),
+            None      => return Err("missing command".to_string()),
+        };
+        let mut model = None;
+        let mut tuning = Tuning::default();
+        let mut precision = Precision::Standard;
+        let mut sample = DEFAULT_SAMPLE;
+        let mut block_size = None;
+        let mut seekable = false;
+        let mut offset = None;
+        let mut length = None;
+        let mut max_output = None;
+        let mut legacy = false;
+        let mut threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let mut csv = false;
+        let mut paths = Vec::new();
+        let mut args = args[1..].iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-m" | "--model" => {
+                    let name = args.next().ok_or("missing model name")?;
+                    model = Some(Model::from_name(name)
+                        .ok_or_else(|| format!("unknown model '{}'", name))?);
+                }
+                "--apm" => {
+                    let stages = args.next().ok_or("missing APM stages")?;
+                    tuning.apm_chain = Some(stages.split(',')
+                        .map(str::parse)
+                        .collect::<std::result::Result<Vec<ApmStage>, _>>()?);
+                }
+                "--halve-at" => tuning.halve_at = Some(parse_number(arg, args.next())?),
+                "--shift"    => tuning.shift    = Some(parse_number(arg, args.next())?),
+                "--init"     => tuning.init     = Some(parse_number(arg, args.next())?),
+                "--limit"    => tuning.limit    = Some(parse_number(arg, args.next())?),
+                "-p" | "--precision" => {
+                    let name = args.next().ok_or("missing precision")?;
+                    precision = Precision::from_name(name)
+                        .ok_or_else(|| format!("unknown precision '{}'", name))?;
+                }
+                "--sample"   => sample = parse_number(arg, args.next())?,
+                "-b" | "--block-size" => {
+                    let size = parse_size(arg, args.next())?;
+                    if size == 0 || size > framed::MAX_BLOCK_SIZE as u64 {
+                        return Err(format!("block size must be from 1 byte to {} bytes", framed::MAX_BLOCK_SIZE));
+                    }
+                    block_size = Some(size as u32);
+                }
+                "--seekable" => seekable = true,
+                "--offset"   => offset = Some(parse_size(arg, args.next())?),
+                "--length"   => length = Some(parse_size(arg, args.next())?),
+                "--max-output" => max_output = Some(parse_size(arg, args.next())?),
+                "--legacy"   => legacy = true,
+                "-t" | "--threads" => {
+                    threads = parse_number(arg, args.next())?;
+                    if threads == 0 {
+                        return Err("at least one thread is needed".to_string());
+                    }
+                }
+                "--csv" => csv = true,
+                "-" => paths.push(arg.clone()),
+                _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+                _ => paths.push(arg.clone()),
+            }
+        }
+        if precision != Precision::Standard && ![Command::Compress, Command::Bench, Command::Tune].contains(&command) {
+            return Err("--precision is only used by compress, bench and tune, other commands read it from the header".to_string());
+        }
+        if block_size.is_some() && command != Command::Compress {
+            return Err("--block-size is only used by compress".to_string());
+        }
+        if seekable && block_size.is_none() {
+            return Err("--seekable needs --block-size".to_string());
+        }
+        let range = match (offset, length) {
+            (None, None) => None,
+            (offset, length) => Some((offset.unwrap_or(0), length)),
+        };
+        if range.is_some() && (command != Command::Decompress || paths.first().is_none_or(|path| path == "-")) {
+            return Err("--offset and --length are only used by decompress, with an input file".to_string());
+        }
+        if max_output.is_some() && command != Command::Decompress && command != Command::Test {
+            return Err("--max-output is only used by decompress and test".to_string());
+        }
+        if legacy && command != Command::Decompress && command != Command::Test {
+            return Err("--legacy is only used by decompress and test".to_string());
+        }
+        if legacy && range.is_some() {
+            return Err("--legacy streams have no index".to_string());
+        }
+        if legacy && model.unwrap_or(DEFAULT_MODEL).legacy_predictor().is_none() {
+            return Err("--legacy is only for fpaq0, fpaq0p, fpaq0f and fpaq0f-apm".to_string());
+        }
+        if csv && command != Command::Bench {
+            return Err("--csv is only used by bench".to_string());
+        }
+        if paths.len() > 2 && command != Command::Bench {
+            return Err("too many arguments".to_string());
+        }
+        let inputs = if paths.is_empty() { vec!["-".to_string()] } else { paths.clone() };
+        let mut paths = paths.into_iter();
+        Ok(Options {
+            command,
+            model,
+            tuning,
+            precision,
+            sample,
+            block_size,
+            seekable,
+            range,
+            max_output,
+            legacy,
+            threads,
+            csv,
+            input:   paths.next().unwrap_or_else(|| "-".to_string()),
+            output:  paths.next(),
+            inputs,
+        })
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(option: &str, value: Option<&String>) -> std::result::Result<T, String> {
+    let value = value.ok_or_else(|| format!("missing value for {}", option))?;
+    value.parse().map_err(|_| format!("invalid value '{}' for {}", value, option))
+}
+// A number of bytes, with an optional K, M or G suffix.
+fn parse_size(option: &str, value: Option<&String>) -> std::result::Result<u64, String> {
+    let value = value.ok_or_else(|| format!("missing value for {}", option))?;
+    let (digits, shift) = match value.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&value[..value.len() - 1], 10),
+        Some('M') => (&value[..value.len() - 1], 20),
+        Some('G') => (&value[..value.len() - 1], 30),
+        _ => (&value[..], 0),
+    };
+    digits.parse::<u64>().ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .ok_or_else(|| format!("invalid value '{}' for {}", value, option))
+}
+
+// Reads the magic bytes at the start of file_in, returning them 
+// and a reader of the whole input.
+fn read_magic<R: Read>(mut file_in: R) -> io::Result<(Vec<u8>, impl Read)> {
+    let mut magic = Vec::with_capacity(4);
+    file_in.by_ref().take(4).read_to_end(&mut magic)?;
+    Ok((magic.clone(), io::Cursor::new(magic).chain(file_in)))
+}
+// Decompresses a single stream or a framed container, 
+// returning the model or container decoded.
+fn decompress_input<R: Read, W: Write>(file_in: R, file_out: &mut W, opts: &Options) -> Result<String> {
+    if opts.legacy {
+        let model = opts.model.unwrap_or(DEFAULT_MODEL);
+        let decompress = DecompressLegacy { file_in, file_out, max_output: opts.max_output };
+        model.with_legacy(decompress).expect("legacy model was checked")?;
+        return Ok(format!("{}, headerless", model));
+    }
+    let (magic, mut file_in) = read_magic(file_in)?;
+    if magic == framed::MAGIC {
+        decompress_framed(&mut file_in, file_out, opts.threads, opts.max_output)?;
+        return Ok("framed".to_string());
+    }
+    Ok(decompress_any(&mut file_in, file_out, opts.max_output)?.model.to_string())
+}
+
+// The length is only trusted for regular files that are not empty. 
+// Pipes and devices report a length that is not what they read, and 
+// files like those in /proc are regular but report 0.
+fn open_input(path: &str) -> io::Result<(Box<dyn Read>, Option<u64>)> {
+    if path == "-" {
+        return Ok((Box::new(io::stdin().lock()), None));
+    }
+    let file = File::open(path)?;
+    let metadata = file.metadata()?;
+    let len = Some(metadata.len()).filter(|&len| metadata.is_file() && len > 0);
+    Ok((Box::new(file), len))
+}
+fn open_output(path: &str) -> io::Result<Box<dyn Write>> {
+    if path == "-" {
+        return Ok(Box::new(io::stdout().lock()));
+    }
+    Ok(Box::new(File::create(path)?))
+}
+// ------------------------------------------------------------------
+
+// Coding -----------------------------------------------------------
+// Compresses file_in to file_out as the compress command does, with 
+// the predictor as its own type (see WithPredictor). Framed blocks 
+// are each compressed by a clone of the fresh predictor.
+struct Compress<'a, R, W> {
+    file_in:   &'a mut R,
+    file_out:  W,
+    len:       Option<u64>,
+    opts:      &'a Options,
+}
+impl<R: Read, W: Write> WithPredictor for Compress<'_, R, W> {
+    type Output = Result<W>;
+    fn run<P: Predictor + Clone + Send + Sync + 'static>(self, predictor: P) -> Result<W> {
+        let Compress { file_in, file_out, len, opts } = self;
+        match opts.block_size {
+            Some(block_size) => {
+                let new_predictor = || predictor.clone();
+                if opts.seekable {
+                    compress_seekable(new_predictor, file_in, file_out, block_size, opts.threads, opts.precision)
+                }
+                else {
+                    compress_framed(new_predictor, file_in, file_out, block_size, opts.threads, opts.precision)
+                }
+            }
+            None => compress_stream(predictor, file_in, file_out, len, opts.precision),
+        }
+    }
+}
+struct DecompressLegacy<'a, R, W> {
+    file_in:    R,
+    file_out:   &'a mut W,
+    max_output: Option<u64>,
+}
+impl<R: Read, W: Write> WithPredictor for DecompressLegacy<'_, R, W> {
+    type Output = Result<()>;
+    fn run<P: Predictor + Clone + Send + Sync + 'static>(self, predictor: P) -> Result<()> {
+        decompress_legacy(predictor, self.file_in, self.file_out, self.max_output)
+    }
+}
+// ------------------------------------------------------------------
+
+// Commands ---------------------------------------------------------
+fn compress(opts: &Options) -> Result<()> {
+    let start_time = Instant::now();
+    let (file_in, len) = open_input(&opts.input)?;
+    let mut file_in  = Counter::new(BufReader::with_capacity(BUF_SIZE, file_in));
+    let file_out     = Counter::new(BufWriter::with_capacity(BUF_SIZE, open_output(opts.output.as_deref().unwrap_or("-"))?));
+
+    let model = opts.model.unwrap_or(DEFAULT_MODEL);
+    let file_out = model.with_tuned(&opts.tuning, Compress { file_in: &mut file_in, file_out, len, opts })??;
+    eprintln!("{} bytes -> {} bytes in {:.2?}", 
+    file_in.count(), file_out.count(), start_time.elapsed());
+    Ok(())
+}
+fn decompress(opts: &Options) -> Result<()> {
+    if let Some((offset, length)) = opts.range {
+        return decompress_part(opts, offset, length);
+    }
+    let start_time = Instant::now();
+    let (file_in, _) = open_input(&opts.input)?;
+    let mut file_in  = Counter::new(BufReader::with_capacity(BUF_SIZE, file_in));
+    let mut file_out = Counter::new(BufWriter::with_capacity(BUF_SIZE, open_output(opts.output.as_deref().unwrap_or("-"))?));
+
+    decompress_input(&mut file_in, &mut file_out, opts)?;
+    eprintln!("{} bytes -> {} bytes in {:.2?}", 
+    file_in.count(), file_out.count(), start_time.elapsed());
+    Ok(())
+}
+fn decompress_part(opts: &Options, offset: u64, length: Option<u64>) -> Result<()> {
+    let start_time = Instant::now();
+    let mut file_in  = BufReader::with_capacity(BUF_SIZE, File::open(&opts.input)?);
+    let mut file_out = BufWriter::with_capacity(BUF_SIZE, open_output(opts.output.as_deref().unwrap_or("-"))?);
+
+    let length = match length {
+        Some(length) => length,
+        None => framed::read_index(&mut file_in)?.1.saturating_sub(offset),
+    };
+    let data = decompress_range(&mut file_in, offset, length, opts.max_output)?;
+    file_out.write_all(&data)?;
+    file_out.flush()?;
+    eprintln!("{} bytes at {} in {:.2?}", data.len(), offset, start_time.elapsed());
+    Ok(())
+}
+fn test(opts: &Options) -> Result<()> {
+    let start_time = Instant::now();
+    let (file_in, _) = open_input(&opts.input)?;
+    let mut file_in  = Counter::new(BufReader::with_capacity(BUF_SIZE, file_in));
+    let mut file_out = Counter::new(io::sink());
+
+    let format = decompress_input(&mut file_in, &mut file_out, opts)?;
+    eprintln!("{}: ok, {} bytes -> {} bytes ({}) in {:.2?}", 
+    opts.input, file_in.count(), file_out.count(), format, start_time.elapsed());
+    Ok(())
+}
+fn info(opts: &Options) -> Result<()> {
+    let (file_in, len) = open_input(&opts.input)?;
+    let (magic, mut file_in) = read_magic(BufReader::new(file_in))?;
+    if magic == framed::MAGIC {
+        let container = framed::read_header(&mut file_in)?;
+        println!("container:  framed{}, {} byte blocks", 
+        if container.seekable { " with index" } else { "" }, container.block_size);
+        if framed::read_u32(&mut file_in)? == 0 {
+            println!("blocks:     none");
+            return Ok(());
+        }
+        println!("first block:");
+    }
+    let header = Header::read(&mut file_in)?;
+    let params = if header.params.is_empty() {
+        "none".to_string()
+    } 
+    else {
+        header.params.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")
+    };
+    println!("version:    {}", header.version);
+    println!("model:      {}", header.model);
+    println!("params:     {}", params);
+    println!("mode:       {}", match header.mode {
+        Mode::Flagged => "flagged",
+        Mode::Length  => "length",
+    });
+    println!("precision:  {}", header.precision.name());
+    match header.len {
+        Some(n) => println!("length:     {} bytes", n),
+        None    => println!("length:     unknown"),
+    }
+    println!("checksum:   crc32");
+    if let Some(len) = len {
+        println!("compressed: {} bytes", len);
+    }
+    Ok(())
+}
+fn bench(opts: &Options) -> Result<()> {
+    let models = match opts.model {
+        Some(model) => vec![model],
+        None        => Model::ALL.to_vec(),
+    };
+    if opts.csv {
+        println!("file,model,size,compressed,bpb,compress_mb_s,decompress_mb_s,memory");
+    }
+    else {
+        println!("{:<20}{:<12}{:>12}{:>12}{:>8}{:>12}{:>12}{:>12}", 
+        "file", "model", "size", "compressed", "bpb", "comp MB/s", "decomp MB/s", "memory");
+    }
+    let mut totals = vec![BenchResult::default(); models.len()];
+    for input in &opts.inputs {
+        let (mut file_in, _) = open_input(input)?;
+        let mut data = Vec::new();
+        file_in.read_to_end(&mut data)?;
+        for (&model, total) in models.iter().zip(totals.iter_mut()) {
+            let result = bench_model(model, opts, &data)?;
+            print_bench_result(input, model, &result, opts.csv);
+            total.add(&result);
+        }
+    }
+    if opts.inputs.len() > 1 {
+        for (&model, total) in models.iter().zip(&totals) {
+            print_bench_result("total", model, total, opts.csv);
+        }
+    }
+    Ok(())
+}
+#[derive(Clone, Default)]
+struct BenchResult {
+    len:              u64,
+    size:             u64,
+    compress_time:    Duration,
+    decompress_time:  Duration,
+    memory:           Option<usize>, // Peak bytes allocated, beyond the input
+}
+impl BenchResult {
+    // Adds the sizes and times of result, keeping the larger memory.
+    fn add(&mut self, result: &BenchResult) {
+        self.len             += result.len;
+        self.size            += result.size;
+        self.compress_time   += result.compress_time;
+        self.decompress_time += result.decompress_time;
+        self.memory           = self.memory.max(result.memory);
+    }
+}
+// Compresses and decompresses data in memory, checking that it decodes.
+fn bench_model(model: Model, opts: &Options, data: &[u8]) -> Result<BenchResult> {
+    let mut decompressed = Vec::with_capacity(data.len());
+
+    let start_time = Instant::now();
+    let (compressed, compress_memory) = peak_memory(|| -> Result<Vec<u8>> {
+        let compress = Compress { file_in: &mut &data[..], file_out: Vec::new(), len: Some(data.len() as u64), opts };
+        model.with_tuned(&opts.tuning, compress)?
+    });
+    let compressed = compressed?;
+    let compress_time = start_time.elapsed();
+
+    // The compressed data is counted while compressing, not decompressing.
+    let start_time = Instant::now();
+    let (result, decompress_memory) = peak_memory(|| decompress_any(&compressed[..], &mut decompressed, None));
+    result?;
+    let decompress_time = start_time.elapsed();
+    if decompressed != data {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, 
+            format!("{} did not decompress to its input", model)).into());
+    }
+
+    Ok(BenchResult {
+        len:              data.len() as u64,
+        size:             compressed.len() as u64,
+        compress_time,
+        decompress_time,
+        memory:           compress_memory.max(decompress_memory),
+    })
+}
+fn print_bench_result(file: &str, model: Model, result: &BenchResult, csv: bool) {
+    let bpb = bpb(result.size, result.len as usize);
+    let compress_speed   = mb_per_s(result.len, result.compress_time);
+    let decompress_speed = mb_per_s(result.len, result.decompress_time);
+    if csv {
+        println!("{},{},{},{},{:.4},{:.3},{:.3},{}", 
+        csv_field(file), model.name(), result.len, result.size, bpb, compress_speed, decompress_speed, 
+        result.memory.map_or(String::new(), |memory| memory.to_string()));
+    }
+    else {
+        println!("{:<20}{:<12}{:>12}{:>12}{:>8.3}{:>12.2}{:>12.2}{:>12}", 
+        file, model.name(), result.len, result.size, bpb, compress_speed, decompress_speed, 
+        result.memory.map_or("-".to_string(), |memory| format!("{:.1} MiB", memory as f64 / (1 << 20) as f64)));
+    }
+}
+fn mb_per_s(len: u64, time: Duration) -> f64 {
+    len as f64 / 1e6 / time.as_secs_f64().max(1e-9)
+}
+// A file name quoted for CSV if it needs to be.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        return format!("\"{}\"", field.replace('"', "\"\""));
+    }
+    field.to_string()
+}
+fn tune(opts: &Options) -> Result<()> {
+    let (file_in, _) = open_input(&opts.input)?;
+    let mut data = Vec::new();
+    // Without an output, only the sample is read.
+    let limit = if opts.output.is_some() { u64::MAX } else { opts.sample as u64 };
+    file_in.take(limit).read_to_end(&mut data)?;
+    let sample = &data[..data.len().min(opts.sample)];
+
+    let models = match opts.model {
+        Some(model) => vec![model],
+        None        => Model::ALL.to_vec(),
+    };
+    let mut best: Option<(Model, Tuning, u64)> = None;
+    for model in models {
+        let start_time = Instant::now();
+        let (tuning, size) = fpaq0::tune::tune(model, sample, opts.precision, |tuning, size| {
+            eprintln!("{:<12}{:>12}{:>8.3}  {}", model.name(), size, bpb(size, sample.len()), tuning_args(tuning));
+        })?;
+        println!("{:<12}{:>12}{:>8.3}  {}  ({:.2?})", 
+        model.name(), size, bpb(size, sample.len()), tuning_args(&tuning), start_time.elapsed());
+        if best.as_ref().is_none_or(|&(_, _, best_size)| size < best_size) {
+            best = Some((model, tuning, size));
+        }
+    }
+    let (model, tuning, _) = best.expect("at least one model is tuned");
+    println!("best: --model {} {}", model.name(), tuning_args(&tuning));
+
+    if let Some(output) = &opts.output {
+        let file_out = Counter::new(BufWriter::with_capacity(BUF_SIZE, open_output(output)?));
+        let compress = Compress { file_in: &mut &data[..], file_out, len: Some(data.len() as u64), opts };
+        let file_out = model.with_tuned(&tuning, compress)??;
+        eprintln!("{} bytes -> {} bytes", data.len(), file_out.count());
+    }
+    Ok(())
+}
+fn bpb(size: u64, len: usize) -> f64 {
+    size as f64 * 8.0 / len.max(1) as f64
+}
+// Tuning as command line options.
+fn tuning_args(tuning: &Tuning) -> String {
+    let mut args = Vec::new();
+    if let Some(halve_at) = tuning.halve_at { args.push(format!("--halve-at {}", halve_at)); }
+    if let Some(shift)    = tuning.shift    { args.push(format!("--shift {}", shift)); }
+    if let Some(init)     = tuning.init     { args.push(format!("--init {}", init)); }
+    if let Some(limit)    = tuning.limit    { args.push(format!("--limit {}", limit)); }
+    if let Some(chain)    = &tuning.apm_chain {
+        let stages = chain.iter().map(ApmStage::to_string).collect::<Vec<_>>();
+        args.push(format!("--apm {}", stages.join(",")));
+    }
+    if args.is_empty() {
+        return "(defaults)".to_string();
+    }
+    args.join(" ")
+}
+// ------------------------------------------------------------------
+
+// Memory -----------------------------------------------------------
+// With the bench-memory feature, the system allocator counts the bytes 
+// allocated and the most allocated at once, so that bench can report 
+// the memory a model uses. Without it, other commands don't pay for
+// the counting and bench reports no memory.
+#[cfg(feature = "bench-memory")]
+mod memory {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    struct CountingAlloc;
+
+    static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+    static PEAK:      AtomicUsize = AtomicUsize::new(0);
+
+    #[global_allocator]
+    static ALLOC: CountingAlloc = CountingAlloc;
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc(layout);
+            if !ptr.is_null() {
+                allocated(layout.size());
+            }
+            ptr
+        }
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            let ptr = System.alloc_zeroed(layout);
+            if !ptr.is_null() {
+                allocated(layout.size());
+            }
+            ptr
+        }
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout);
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let new_ptr = System.realloc(ptr, layout, new_size);
+            if !new_ptr.is_null() {
+                if new_size > layout.size() {
+                    allocated(new_size - layout.size());
+                }
+                else {
+                    ALLOCATED.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+                }
+            }
+            new_ptr
+        }
+    }
+    fn allocated(size: usize) {
+        let total = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(total, Ordering::Relaxed);
+    }
+    // The most bytes allocated at once while f runs, beyond those 
+    // allocated before it.
+    pub fn peak_memory<T>(f: impl FnOnce() -> T) -> (T, Option<usize>) {
+        let base = ALLOCATED.load(Ordering::Relaxed);
+        PEAK.store(base, Ordering::Relaxed);
+        let result = f();
+        (result, Some(PEAK.load(Ordering::Relaxed) - base))
+    }
+}
+#[cfg(feature = "bench-memory")]
+use memory::peak_memory;
+
+#[cfg(not(feature = "bench-memory"))]
+fn peak_memory<T>(f: impl FnOnce() -> T) -> (T, Option<usize>) {
+    (f(), None)
+}
+// ------------------------------------------------------------------
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    let opts = match Options::parse(&args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    if opts.model.is_some() && !opts.legacy && ![Command::Compress, Command::Bench, Command::Tune].contains(&opts.command) {
+        eprintln!("Error: --model is only used by compress, bench, tune and --legacy, other commands read it from the header");
+        process::exit(2);
+    }
+    if opts.tuning != Tuning::default() {
+        let model = match (&opts.command, opts.model) {
+            (Command::Compress | Command::Bench, Some(model)) => model,
+            (Command::Compress, None) => DEFAULT_MODEL,
+            (Command::Bench, None) => {
+                eprintln!("Error: bench needs --model when model parameters are given");
+                process::exit(2);
+            }
+            _ => {
+                eprintln!("Error: model parameters are only used by compress and bench, other commands read them from the header");
+                process::exit(2);
+            }
+        };
+        if let Err(e) = opts.tuning.check(model) {
+            eprintln!("Error: {}", e);
+            process::exit(2);
+        }
+    }
+    let result = match opts.command {
+        Command::Compress   => compress(&opts),
+        Command::Decompress => decompress(&opts),
+        Command::Test       => test(&opts),
+        Command::Info       => info(&opts),
+        Command::Bench      => bench(&opts),
+        Command::Tune       => tune(&opts),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::statemap::StateMap;
+
+const MIN_LEN: usize = 4;      // Shortest match, and number of bytes hashed
+const MAX_LEN: usize = 65535;
+const MAX_VERIFY: usize = 32;  // Longest match found when starting a new one
+
+// Match Model ------------------------------------------------------
+// Finds the most recent occurrence of the last MIN_LEN bytes in a 
+// history buffer and predicts that the byte following it comes next.
+// The expected bit and length of the match select a StateMap context, 
+// so confidence grows with the match length.
+#[derive(Clone)]
+pub struct MatchModel {
+    buf:      Vec<u8>,  // History buffer
+    pos:      usize,    // Number of bytes seen
+    ht:       Vec<u32>, // Maps a hash of the last MIN_LEN bytes to a position
+    ht_bits:  u32,
+    ptr:      usize,    // Position of the predicted byte
+    len:      usize,    // Length of the current match, 0 if none
+    c4:       u32,      // Last 4 bytes
+    cxt:      usize,    // Partial byte with a leading 1
+    sm:       StateMap,
+}
+impl MatchModel {
+    // History of 2^bits bytes, hash table of 2^(bits - 2) positions.
+    pub fn new(bits: u32) -> MatchModel {
+        MatchModel {
+            buf:      vec![0; 1 << bits],
+            pos:      0,
+            ht:       vec![0; 1 << (bits - 2)],
+            ht_bits:  bits - 2,
+            ptr:      0,
+            len:      0,
+            c4:       0,
+            cxt:      1,
+            sm:       StateMap::new(64),
+        }
+    }
+    #[inline]
+    pub fn has_match(&self) -> bool {
+        self.len > 0
+    }
+    fn expected_bit(&self) -> i32 {
+        let expected = self.buf[self.ptr & (self.buf.len() - 1)] as usize | 256;
+        let bits = (usize::BITS - 1 - self.cxt.leading_zeros()) as usize; // Bits of the byte seen so far
+        ((expected >> (7 - bits)) & 1) as i32
+    }
+    #[inline]
+    pub fn p(&mut self) -> i32 {
+        let cxt = if self.len > 0 {
+            (self.len.min(31) << 1) | self.expected_bit() as usize
+        } 
+        else {
+            0
+        };
+        self.sm.p(cxt)
+    }
+    #[inline]
+    pub fn update(&mut self, bit: i32) {
+        self.sm.update(bit);
+        if self.len > 0 && bit != self.expected_bit() {
+            self.len = 0;
+        }
+        self.cxt = self.cxt * 2 + bit as usize;
+        if self.cxt < 256 { 
+            return; 
+        }
+        let byte = (self.cxt - 256) as u8;
+        let mask = self.buf.len() - 1;
+        self.cxt = 1;
+        self.buf[self.pos & mask] = byte;
+        self.pos += 1;
+        self.c4 = (self.c4 << 8) | byte as u32;
+        if self.pos < MIN_LEN { 
+            return; 
+        }
+
+        let h = (self.c4.wrapping_mul(123456791) >> (32 - self.ht_bits)) as usize;
+        if self.len > 0 {
+            self.len = (self.len + 1).min(MAX_LEN);
+            self.ptr += 1;
+        }
+        else {
+            // Check the most recent occurrence of this hash for a match
+            let dist = (self.pos as u32).wrapping_sub(self.ht[h]) as usize;
+            if self.ht[h] != 0 && dist < self.buf.len() {
+                let cand = self.pos - dist;
+                let mut len = 0;
+                while len < MAX_VERIFY && len < cand 
+                && self.buf[(cand - 1 - len) & mask] == self.buf[(self.pos - 1 - len) & mask] {
+                    len += 1;
+                }
+                if len >= MIN_LEN {
+                    self.len = len;
+                    self.ptr = cand;
+                }
+            }
+        }
+        self.ht[h] = self.pos as u32;
+    }
+}
+// ------------------------------------------------------------------
@@ -0,0 +1,55 @@
+use crate::logistic::squash;
+
+// Mixer ------------------------------------------------------------
+// Combines predictions in the stretched domain with a weighted sum, 
+// selecting one of n_sets weight sets per prediction. Weights are 
+// trained online to reduce the coding cost of each bit.
+#[derive(Clone)]
+pub struct Mixer {
+    n:        usize,    // Number of inputs
+    inputs:   Vec<i32>, // Stretched predictions (-2047..2047)
+    weights:  Vec<i32>, // n_sets sets of n weights (16 bit fixed point)
+    set:      usize,    // Index of first weight of the selected set
+    rate:     i32,      // Learning rate (higher = faster)
+    pr:       i32,
+}
+impl Mixer {
+    pub fn new(n: usize, n_sets: usize, rate: i32) -> Mixer {
+        Mixer {
+            n,
+            inputs:   Vec::with_capacity(n),
+            weights:  vec![(1 << 16) / n as i32; n * n_sets],
+            set:      0,
+            rate,
+            pr:       2048,
+        }
+    }
+    #[inline]
+    pub fn add(&mut self, st: i32) {
+        assert!(self.inputs.len() < self.n);
+        self.inputs.push(st);
+    }
+    #[inline]
+    pub fn p(&mut self, set: usize) -> i32 {
+        assert!(self.inputs.len() == self.n);
+        self.set = set * self.n;
+        let weights = &self.weights[self.set..self.set + self.n];
+        let dot: i64 = self.inputs.iter().zip(weights)
+            .map(|(&x, &w)| x as i64 * w as i64)
+            .sum();
+        self.pr = squash((dot >> 16).clamp(-2047, 2047) as i32);
+        self.pr
+    }
+    #[inline]
+    pub fn update(&mut self, bit: i32) {
+        let err = ((bit << 12) - self.pr) * self.rate;
+        let weights = &mut self.weights[self.set..self.set + self.n];
+        // Inputs stuck at opposite extremes can grow a pair of weights 
+        // without bound while the prediction stays clamped.
+        for (w, &x) in weights.iter_mut().zip(self.inputs.iter()) {
+            *w = w.saturating_add((x * err) >> 12);
+        }
+        self.inputs.clear();
+    }
+}
+// ------------------------------------------------------------------
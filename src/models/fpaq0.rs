@@ -0,0 +1,85 @@
+use std::ops::RangeInclusive;
+use crate::{
+    models::Model,
+    predictor::Predictor,
+};
+
+// Fpaq0 -----------------------------------------------------------
+// Order-0 model keeping a 0 and 1 count for each partial byte context.
+// Both counts are halved when one exceeds halve_at, so a lower limit 
+// adapts faster and a higher one suits stationary data. Context 1 
+// predicts the flag before each byte (0 = another byte follows).
+#[derive(Clone)]
+pub struct Fpaq0 {
+    cxt:       usize,
+    cxts:      [[u32; 2]; 512],
+    halve_at:  u32,
+}
+impl Fpaq0 {
+    pub const HALVE_AT: u32 = 65534;
+    pub const HALVE_AT_RANGE: RangeInclusive<u32> = 2..=(1 << 19); // 4096 * count fits in a u32
+
+    pub fn new() -> Fpaq0 {
+        Fpaq0::with_halve_at(Fpaq0::HALVE_AT)
+    }
+    pub fn with_halve_at(halve_at: u32) -> Fpaq0 {
+        assert!(Fpaq0::HALVE_AT_RANGE.contains(&halve_at));
+        Fpaq0 {
+            cxt:       1, 
+            cxts:      [[0; 2]; 512],
+            halve_at,
+        }
+    }
+    // Params are the halving limit (4, LE).
+    pub fn from_params(params: &[u8]) -> Option<Fpaq0> {
+        match *params {
+            [a, b, c, d] => {
+                let halve_at = u32::from_le_bytes([a, b, c, d]);
+                Fpaq0::HALVE_AT_RANGE.contains(&halve_at).then(|| Fpaq0::with_halve_at(halve_at))
+            }
+            _ => None,
+        }
+    }
+}
+impl Default for Fpaq0 {
+    fn default() -> Fpaq0 {
+        Fpaq0::new()
+    }
+}
+impl Predictor for Fpaq0 {
+    #[inline]
+    fn p(&mut self) -> u32 { 
+        4096 * (self.cxts[self.cxt][1] + 1) / 
+        (self.cxts[self.cxt][0] + self.cxts[self.cxt][1] + 2) 
+    } 
+    #[inline]
+    fn p16(&mut self) -> u32 { 
+        let [n0, n1] = self.cxts[self.cxt].map(u64::from);
+        (65536 * (n1 + 1) / (n0 + n1 + 2)) as u32
+    } 
+    #[inline]
+    fn update(&mut self, bit: i32) {
+        let bit = bit as usize;
+        self.cxts[self.cxt][bit] += 1;
+        let bit_count: u32 = self.cxts[self.cxt][bit]; 
+        if bit_count > self.halve_at {
+            self.cxts[self.cxt][0] >>= 1;
+            self.cxts[self.cxt][1] >>= 1;   
+        } 
+        self.cxt = self.cxt * 2 + bit;
+        if self.cxt >= 512 {
+            self.cxt = 1;
+        } 
+    }
+    #[inline]
+    fn flag(&self) -> Option<i32> {
+        Some(0)
+    }
+    fn model(&self) -> Model {
+        Model::Fpaq0
+    }
+    fn params(&self) -> Vec<u8> {
+        self.halve_at.to_le_bytes().to_vec()
+    }
+}
+// ------------------------------------------------------------------
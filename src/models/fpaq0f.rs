@@ -0,0 +1,89 @@
+use crate::{
+    models::{limit_from_params, Model},
+    predictor::Predictor,
+    state_table::next_state,
+    statemap::{StateMap, LIMIT},
+};
+
+// Fpaq0f ----------------------------------------------------------
+// Order-0 model mapping each partial byte context and its bit history 
+// state to a prediction through a StateMap. Context 0 predicts the 
+// flag before each byte (1 = another byte follows).
+#[derive(Clone)]
+pub struct Fpaq0f {
+    cxt:            usize,
+    sm:             StateMap,
+    state:          [u8; 256],
+    limit:          u16,
+    implied_flags:  bool,     // Updates with the flag itself, see without_flags
+}
+impl Fpaq0f {
+    pub fn new() -> Fpaq0f {
+        Fpaq0f::with_limit(LIMIT)
+    }
+    // limit is the StateMap count limit, in LIMIT_RANGE.
+    pub fn with_limit(limit: u16) -> Fpaq0f {
+        Fpaq0f {
+            cxt:            0,
+            sm:             StateMap::with_limit(65536, limit),
+            state:          [0; 256],
+            limit,
+            implied_flags:  false,
+        }
+    }
+    // For models that code no flags (Mix). The flag that another byte 
+    // follows is applied before every byte, so that the contexts stay
+    // aligned to bytes and predict as in a stream with flags.
+    pub fn without_flags(limit: u16) -> Fpaq0f {
+        let mut fpaq0f = Fpaq0f { implied_flags: true, ..Fpaq0f::with_limit(limit) };
+        fpaq0f.imply_flag();
+        fpaq0f
+    }
+    fn imply_flag(&mut self) {
+        self.p();
+        self.update(1);
+    }
+    pub fn from_params(params: &[u8]) -> Option<Fpaq0f> {
+        limit_from_params(params).map(Fpaq0f::with_limit)
+    }
+}
+impl Default for Fpaq0f {
+    fn default() -> Fpaq0f {
+        Fpaq0f::new()
+    }
+}
+impl Predictor for Fpaq0f {
+    #[inline]
+    fn p(&mut self) -> u32 { 
+        self.sm.p(self.cxt * 256 + self.state[self.cxt] as usize) as u32
+    } 
+    #[inline]
+    fn p16(&mut self) -> u32 { 
+        self.sm.p16(self.cxt * 256 + self.state[self.cxt] as usize) as u32
+    } 
+    #[inline]
+    fn update(&mut self, bit: i32) {
+        self.sm.update(bit);
+
+        self.state[self.cxt] = next_state(self.state[self.cxt], bit);
+
+        self.cxt = self.cxt * 2 + bit as usize;
+        if self.cxt >= 256 { 
+            self.cxt = 0; 
+            if self.implied_flags {
+                self.imply_flag();
+            }
+        }
+    }
+    #[inline]
+    fn flag(&self) -> Option<i32> {
+        if self.implied_flags { None } else { Some(1) }
+    }
+    fn model(&self) -> Model {
+        Model::Fpaq0f
+    }
+    fn params(&self) -> Vec<u8> {
+        self.limit.to_le_bytes().to_vec()
+    }
+}
+// -----------------------------------------------------------------
@@ -0,0 +1,405 @@
+use crate::{
+    apm::Apm,
+    logistic::{squash, Stretch},
+    match_model::MatchModel,
+    models::{limit_from_params, Model, LIMIT, LIMIT_RANGE},
+    predictor::Predictor,
+    state_table::next_state,
+};
+
+#[allow(overflowing_literals)]
+const PR_MSK: i32 = 0xFFFFFE00; // High 23 bit mask
+
+pub const MATCH_BITS: u8 = 22; // Default match model history size (log2 bytes)
+pub const MATCH_BITS_RANGE: std::ops::RangeInclusive<u8> = 16..=28;
+
+// StateMap --------------------------------------------------------
+// Variant of the fpaq0f StateMap taken from bbb, which updates the 
+// previous context as part of computing the next prediction.
+#[derive(Clone)]
+struct StateMap {
+    limit:    usize,     // Count limit: controls rate of adaptation
+    cxt:      usize,         
+    cxt_map:  Vec<u32>,  // Maps a context to a prediction and a count 
+    rec_t:    Vec<u16>,  // Controls adjustment to cxt_map
+}
+impl StateMap {
+    fn new(n: usize, limit: u16) -> StateMap {
+        StateMap { 
+            limit:    limit as usize,
+            cxt:      0,
+            cxt_map:  vec![1 << 31; n],
+            rec_t:    (0..512).map(|i| 32768/(i+i+5)).collect(),
+        }
+    }
+    #[inline]
+    fn p(&mut self, bit: i32, cxt: usize) -> i32 {
+        assert!(bit == 0 || bit == 1);
+        self.update(bit);                      
+        self.cxt = cxt;
+        (self.cxt_map[self.cxt] >> 20) as i32  
+    }
+    #[inline]
+    fn update(&mut self, bit: i32) {
+        let count = (self.cxt_map[self.cxt] & 511) as usize; // Low 9 bits
+        let pr = (self.cxt_map[self.cxt] >> 14) as i32;      // High 18 bits
+
+        if count < self.limit { self.cxt_map[self.cxt] += 1; }
+
+        // Update cxt_map based on prediction error
+        let pr_err = (bit << 18) - pr; // Prediction error
+        let rec_v = self.rec_t[count] as i32; // Reciprocal value
+        self.cxt_map[self.cxt] = 
+        self.cxt_map[self.cxt].wrapping_add(((pr_err * rec_v) & PR_MSK) as u32); 
+    }
+}
+// -----------------------------------------------------------------
+
+// APM Chain -------------------------------------------------------
+// Selects the context of an APM stage from the partial byte (cxt) 
+// and the last 4 bytes (cxt4).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ApmContext {
+    Order0,    // Partial byte
+    Order1,    // Partial byte and the last byte
+    Byte2Low5, // Partial byte and the low 5 bits of the byte before the last
+    Hash16,    // Partial byte xor a hash of the last 2 bytes
+    Hash24,    // Partial byte xor a hash of the last 3 bytes
+}
+impl ApmContext {
+    pub const ALL: [ApmContext; 5] = [
+        ApmContext::Order0,
+        ApmContext::Order1,
+        ApmContext::Byte2Low5,
+        ApmContext::Hash16,
+        ApmContext::Hash24,
+    ];
+
+    pub fn id(self) -> u8 {
+        match self {
+            ApmContext::Order0    => 0,
+            ApmContext::Order1    => 1,
+            ApmContext::Byte2Low5 => 2,
+            ApmContext::Hash16    => 3,
+            ApmContext::Hash24    => 4,
+        }
+    }
+    pub fn from_id(id: u8) -> Option<ApmContext> {
+        ApmContext::ALL.into_iter().find(|cxt| cxt.id() == id)
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            ApmContext::Order0    => "order0",
+            ApmContext::Order1    => "order1",
+            ApmContext::Byte2Low5 => "byte2low5",
+            ApmContext::Hash16    => "hash16",
+            ApmContext::Hash24    => "hash24",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<ApmContext> {
+        ApmContext::ALL.into_iter().find(|cxt| cxt.name() == name)
+    }
+    // Fewest table size bits that hold every context.
+    fn min_bits(self) -> u8 {
+        match self {
+            ApmContext::Order0    => 8,
+            ApmContext::Order1    => 16,
+            ApmContext::Byte2Low5 => 13,
+            ApmContext::Hash16    => 8,
+            ApmContext::Hash24    => 8,
+        }
+    }
+    #[inline(always)]
+    fn select(self, bits: u8, cxt: usize, cxt4: usize) -> usize {
+        let hash = |x: usize| ((x as u32).wrapping_mul(123456791) >> (32 - bits as u32)) as usize;
+        match self {
+            ApmContext::Order0    => cxt,
+            ApmContext::Order1    => cxt | ((cxt4 << 8) & 0xFF00),
+            ApmContext::Byte2Low5 => cxt | (cxt4 & 0x1F00),
+            ApmContext::Hash16    => cxt ^ hash(cxt4 & 0xFFFF),
+            ApmContext::Hash24    => cxt ^ hash(cxt4 & 0xFFFFFF),
+        }
+    }
+}
+impl std::fmt::Display for ApmContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// One APM in the chain. The APM refines the previous stage's output 
+// (or, for a parallel stage, the previous stage's input) and its 
+// prediction is blended with the previous stage's output:
+// pr = (apm * weight + pr * (4 - weight) + 2) >> 2
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ApmStage {
+    pub context:   ApmContext,
+    pub bits:      u8,   // Table size (log2 contexts)
+    pub rate:      u8,   // Adaptation rate (higher = slower) (1..=15)
+    pub weight:    u8,   // Blend weight in quarters (1..=4)
+    pub parallel:  bool,
+}
+impl ApmStage {
+    pub const MAX_BITS: u8 = 20;
+    pub const MAX_STAGES: usize = 16;
+    pub const MAX_CONTEXTS: usize = 1 << 21; // Of all the stages of a chain (66 bytes each)
+
+    pub const fn new(context: ApmContext, bits: u8, rate: u8, weight: u8) -> ApmStage {
+        ApmStage { context, bits, rate, weight, parallel: false }
+    }
+    pub const fn parallel(self) -> ApmStage {
+        ApmStage { parallel: true, ..self }
+    }
+    pub fn is_valid(&self) -> bool {
+        (self.context.min_bits()..=ApmStage::MAX_BITS).contains(&self.bits)
+        && (1..=15).contains(&self.rate)
+        && (1..=4).contains(&self.weight)
+    }
+}
+impl std::fmt::Display for ApmStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}:{}:{}", self.context, self.bits, self.rate, self.weight)?;
+        if self.parallel {
+            write!(f, ":p")?;
+        }
+        Ok(())
+    }
+}
+impl std::str::FromStr for ApmStage {
+    type Err = String;
+
+    // context:bits:rate:weight, with a trailing :p for a parallel stage.
+    fn from_str(s: &str) -> Result<ApmStage, String> {
+        let fields: Vec<&str> = s.split(':').collect();
+        let (fields, parallel) = match fields.split_last() {
+            Some((&"p", rest)) => (rest, true),
+            _ => (&fields[..], false),
+        };
+        let [context, bits, rate, weight] = fields else {
+            return Err(format!("APM stage '{}' is not context:bits:rate:weight[:p]", s));
+        };
+        let context = ApmContext::from_name(context)
+            .ok_or_else(|| format!("unknown APM context '{}'", context))?;
+        let num = |field: &str| field.parse::<u8>()
+            .map_err(|_| format!("invalid number '{}' in APM stage '{}'", field, s));
+        let stage = ApmStage { context, bits: num(bits)?, rate: num(rate)?, weight: num(weight)?, parallel };
+        if !stage.is_valid() {
+            return Err(format!("APM stage '{}' is out of range", s));
+        }
+        Ok(stage)
+    }
+}
+
+// The chain from bbb.
+pub const DEFAULT_APM_CHAIN: [ApmStage; 5] = [
+    ApmStage::new(ApmContext::Order0,    8,  5, 4),
+    ApmStage::new(ApmContext::Order0,    8,  9, 2).parallel(),
+    ApmStage::new(ApmContext::Order1,    16, 7, 4),
+    ApmStage::new(ApmContext::Byte2Low5, 13, 7, 3),
+    ApmStage::new(ApmContext::Hash24,    14, 7, 2),
+];
+
+// A chain is valid if every stage is in range, the tables fit in 
+// MAX_CONTEXTS (so a header can't ask the decoder for gigabytes) and 
+// the first stage is not parallel (there is no previous stage input).
+pub fn is_valid_apm_chain(chain: &[ApmStage]) -> bool {
+    chain.len() <= ApmStage::MAX_STAGES 
+    && chain.iter().all(ApmStage::is_valid) 
+    && chain.iter().map(|stage| 1 << stage.bits).sum::<usize>() <= ApmStage::MAX_CONTEXTS
+    && !chain.first().is_some_and(|stage| stage.parallel)
+}
+fn chain_params(chain: &[ApmStage]) -> Vec<u8> {
+    let mut params = vec![chain.len() as u8];
+    for stage in chain {
+        params.extend([stage.context.id(), stage.bits, stage.rate, stage.weight, stage.parallel as u8]);
+    }
+    params
+}
+// Runs the APMs of a chain on pr, returning the refined pr and, if 
+// high, pr16. pr16 follows pr through the chain, blending the APMs' 
+// 16 bit outputs, while pr is kept as it was for 12 bit streams.
+#[inline(always)]
+fn run_chain(
+    chain: &[ApmStage], apms: &mut [Apm], bit: i32, mut pr: i32, 
+    cxt: usize, cxt4: usize, high: bool
+) -> (i32, i32) {
+    let mut input = pr;
+    let mut pr16 = pr << 4;
+    for (stage, apm) in chain.iter().zip(apms.iter_mut()) {
+        if !stage.parallel {
+            input = pr;
+        }
+        let w = stage.weight as i32;
+        let pa = apm.p16(bit, stage.rate as i32, input, stage.context.select(stage.bits, cxt, cxt4));
+        pr = ((pa >> 4) * w + pr * (4 - w) + 2) >> 2;
+        if high {
+            pr16 = (pa * w + pr16 * (4 - w) + 2) >> 2;
+        }
+    }
+    (pr, pr16)
+}
+// -----------------------------------------------------------------
+
+// Fpaq0fApm -------------------------------------------------------
+// fpaq0f with its StateMap prediction refined by a chain of Adaptive 
+// Probability Maps (by default the 5 from bbb). While the match model 
+// has a match, its prediction is averaged with the StateMap's 
+// (weighted 3 to 1 in the stretched domain) before the APMs. As in 
+// fpaq0f, context 0 predicts the flag before each byte, which the 
+// match model doesn't see.
+//
+// Params: match model history size (0 = none) | number of stages | 
+// context, bits, rate, weight, parallel for each stage | StateMap 
+// limit (2, LE).
+#[derive(Clone)]
+pub struct Fpaq0fApm {
+    cxt:     usize,
+    cxt4:    usize,
+    pr:      i32,
+    pr16:    i32,      // pr with 16 bits, from the APMs' full precision
+    high:    bool,     // Whether p16 has been asked for
+    state:   [u8; 256],
+    sm:      StateMap,
+    mm:      Option<(MatchModel, u8)>, // Match model and its history size
+    s:       Stretch,
+    chain:   Vec<ApmStage>,
+    apms:    Vec<Apm>, // One for each stage of the chain
+    default: bool,     // Whether the chain is DEFAULT_APM_CHAIN
+    limit:   u16,
+}
+impl Fpaq0fApm {
+    pub fn new() -> Fpaq0fApm {
+        Fpaq0fApm::with_config(Some(MATCH_BITS), &DEFAULT_APM_CHAIN, LIMIT)
+    }
+    pub fn with_match_bits(match_bits: Option<u8>) -> Fpaq0fApm {
+        Fpaq0fApm::with_config(match_bits, &DEFAULT_APM_CHAIN, LIMIT)
+    }
+    pub fn with_apm_chain(chain: &[ApmStage]) -> Fpaq0fApm {
+        Fpaq0fApm::with_config(Some(MATCH_BITS), chain, LIMIT)
+    }
+    // match_bits is the log2 size of the match model's history buffer 
+    // (in MATCH_BITS_RANGE), or None for no match model. limit is the 
+    // StateMap count limit, in LIMIT_RANGE.
+    pub fn with_config(match_bits: Option<u8>, chain: &[ApmStage], limit: u16) -> Fpaq0fApm {
+        if let Some(bits) = match_bits {
+            assert!(MATCH_BITS_RANGE.contains(&bits));
+        }
+        assert!(is_valid_apm_chain(chain));
+        assert!(LIMIT_RANGE.contains(&limit));
+        Fpaq0fApm {
+            cxt:     0,
+            cxt4:    0,
+            pr:      2048,
+            pr16:    32768,
+            high:    false,
+            state:   [0; 256],
+            sm:      StateMap::new(65536, limit),
+            mm:      match_bits.map(|bits| (MatchModel::new(bits as u32), bits)),
+            s:       Stretch::new(),
+            chain:   chain.to_vec(),
+            apms:    chain.iter().map(|stage| Apm::new(1 << stage.bits)).collect(),
+            default: chain == DEFAULT_APM_CHAIN,
+            limit,
+        }
+    }
+    pub fn from_params(params: &[u8]) -> Option<Fpaq0fApm> {
+        let [bits, n, ref rest @ ..] = *params else {
+            return None;
+        };
+        if rest.len() != n as usize * 5 + 2 {
+            return None;
+        }
+        let match_bits = match bits {
+            0 => None,
+            _ if MATCH_BITS_RANGE.contains(&bits) => Some(bits),
+            _ => return None,
+        };
+        let (stages, limit) = rest.split_at(n as usize * 5);
+        let chain = stages.chunks(5).map(|stage| Some(ApmStage {
+            context:   ApmContext::from_id(stage[0])?,
+            bits:      stage[1],
+            rate:      stage[2],
+            weight:    stage[3],
+            parallel:  match stage[4] { 0 => false, 1 => true, _ => return None },
+        })).collect::<Option<Vec<ApmStage>>>()?;
+        if !is_valid_apm_chain(&chain) {
+            return None;
+        }
+        Some(Fpaq0fApm::with_config(match_bits, &chain, limit_from_params(limit)?))
+    }
+    pub fn apm_chain(&self) -> Vec<ApmStage> {
+        self.chain.clone()
+    }
+}
+impl Default for Fpaq0fApm {
+    fn default() -> Fpaq0fApm {
+        Fpaq0fApm::new()
+    }
+}
+impl Predictor for Fpaq0fApm {
+    #[inline]
+    fn p(&mut self) -> u32 { 
+        assert!(self.pr >= 0 && self.pr < 4096);
+        self.pr as u32
+    } 
+    // Only the high precision coder asks for p16, before the first 
+    // update, so update() only computes pr16 for it.
+    #[inline]
+    fn p16(&mut self) -> u32 { 
+        self.high = true;
+        assert!(self.pr16 >= 0 && self.pr16 < 65536);
+        self.pr16 as u32
+    } 
+    #[inline]
+    fn update(&mut self, bit: i32) {
+        assert!(bit == 0 || bit == 1);
+        let flag = self.cxt == 0;
+        self.state[self.cxt] = next_state(self.state[self.cxt], bit);
+
+        self.cxt = self.cxt * 2 + bit as usize;
+        if self.cxt >= 256 {
+            self.cxt4 = (self.cxt4 << 8) | (self.cxt - 256);  // Shift new byte into cxt4
+            self.cxt = 0;
+        }
+
+        // SSE
+        self.pr = self.sm.p(bit, self.state[self.cxt] as usize);
+
+        if let Some((mm, _)) = self.mm.as_mut() {
+            if !flag {
+                mm.update(bit);
+            }
+            if self.cxt != 0 {
+                let pm = mm.p();
+                if mm.has_match() {
+                    self.pr = squash((self.s.stretch(self.pr) + self.s.stretch(pm) * 3) >> 2);
+                }
+            }
+        }
+
+        // The default chain is run as a constant, so the compiler can 
+        // unroll it with its stages folded in.
+        let (pr, pr16) = if self.default {
+            run_chain(&DEFAULT_APM_CHAIN, &mut self.apms, bit, self.pr, self.cxt, self.cxt4, self.high)
+        }
+        else {
+            run_chain(&self.chain, &mut self.apms, bit, self.pr, self.cxt, self.cxt4, self.high)
+        };
+        self.pr = pr;
+        self.pr16 = pr16;
+    }   
+    #[inline]
+    fn flag(&self) -> Option<i32> {
+        Some(1)
+    }
+    fn model(&self) -> Model {
+        Model::Fpaq0fApm
+    }
+    fn params(&self) -> Vec<u8> {
+        let mut params = vec![self.mm.as_ref().map_or(0, |&(_, bits)| bits)];
+        params.extend(chain_params(&self.apm_chain()));
+        params.extend(self.limit.to_le_bytes());
+        params
+    }
+}
+// -----------------------------------------------------------------
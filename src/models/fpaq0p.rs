@@ -0,0 +1,85 @@
+use std::ops::RangeInclusive;
+use crate::{
+    models::Model,
+    predictor::Predictor,
+};
+
+// Fpaq0p ----------------------------------------------------------
+// Order-0 model keeping a 16 bit probability for each partial byte 
+// context, adjusted by 1/2^shift (by default 1/32) of the error after 
+// each bit. Context 1 predicts the flag before each byte (0 = another 
+// byte follows).
+#[derive(Clone)]
+pub struct Fpaq0p {
+    context:     usize,
+    context_map: [u32; 512], // maps context to probability
+    shift:       u8,
+    init:        u16,        // Initial probability
+}
+impl Fpaq0p {
+    pub const SHIFT: u8 = 5;
+    pub const SHIFT_RANGE: RangeInclusive<u8> = 1..=15;
+    pub const INIT: u16 = 32768;
+    pub const INIT_RANGE: RangeInclusive<u16> = 1..=65535;
+
+    pub fn new() -> Fpaq0p {
+        Fpaq0p::with_rate(Fpaq0p::SHIFT, Fpaq0p::INIT)
+    }
+    pub fn with_rate(shift: u8, init: u16) -> Fpaq0p {
+        assert!(Fpaq0p::SHIFT_RANGE.contains(&shift) && Fpaq0p::INIT_RANGE.contains(&init));
+        Fpaq0p {
+            context:     1, 
+            context_map: [init as u32; 512],
+            shift,
+            init,
+        }
+    }
+    // Params are the shift (1) and the initial probability (2, LE).
+    pub fn from_params(params: &[u8]) -> Option<Fpaq0p> {
+        match *params {
+            [shift, a, b] => {
+                let init = u16::from_le_bytes([a, b]);
+                (Fpaq0p::SHIFT_RANGE.contains(&shift) && Fpaq0p::INIT_RANGE.contains(&init))
+                    .then(|| Fpaq0p::with_rate(shift, init))
+            }
+            _ => None,
+        }
+    }
+}
+impl Default for Fpaq0p {
+    fn default() -> Fpaq0p {
+        Fpaq0p::new()
+    }
+}
+impl Predictor for Fpaq0p {
+    #[inline]
+    fn p(&mut self) -> u32 { 
+        self.context_map[self.context] >> 4
+    } 
+    #[inline]
+    fn p16(&mut self) -> u32 { 
+        self.context_map[self.context]
+    } 
+    #[inline]
+    fn update(&mut self, bit: i32) {
+        if bit == 1 { 
+            self.context_map[self.context] += (65536 - self.context_map[self.context]) >> self.shift; 
+        } else { 
+            self.context_map[self.context] -= self.context_map[self.context] >> self.shift; 
+        }
+        self.context = self.context * 2 + bit as usize;
+        if self.context >= 512 { self.context = 1; }
+    }
+    #[inline]
+    fn flag(&self) -> Option<i32> {
+        Some(0)
+    }
+    fn model(&self) -> Model {
+        Model::Fpaq0p
+    }
+    fn params(&self) -> Vec<u8> {
+        let [a, b] = self.init.to_le_bytes();
+        vec![self.shift, a, b]
+    }
+}
+// -----------------------------------------------------------------
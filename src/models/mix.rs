@@ -0,0 +1,75 @@
+use crate::{
+    logistic::Stretch,
+    mixer::Mixer,
+    models::{limit_from_params, Fpaq0f, Model, OrderN},
+    predictor::Predictor,
+    statemap::LIMIT,
+};
+
+const MIXER_RATE: i32 = 3; // Mixer learning rate
+
+// Mix -------------------------------------------------------------
+// Blends the order 0 (fpaq0f), order 1 and order 2 StateMap models 
+// with a Mixer, using a weight set selected by the partial byte.
+#[derive(Clone)]
+pub struct Mix {
+    cxt:     usize,
+    order0:  Fpaq0f,
+    order1:  OrderN,
+    order2:  OrderN,
+    mixer:   Mixer,
+    s:       Stretch,
+    limit:   u16,
+}
+impl Mix {
+    pub fn new() -> Mix {
+        Mix::with_limit(LIMIT)
+    }
+    // limit is the StateMap count limit of all three models, in LIMIT_RANGE.
+    pub fn with_limit(limit: u16) -> Mix {
+        Mix {
+            cxt:     1,
+            order0:  Fpaq0f::without_flags(limit),
+            order1:  OrderN::with_limit(1, limit),
+            order2:  OrderN::with_limit(2, limit),
+            mixer:   Mixer::new(4, 256, MIXER_RATE),
+            s:       Stretch::new(),
+            limit,
+        }
+    }
+    pub fn from_params(params: &[u8]) -> Option<Mix> {
+        limit_from_params(params).map(Mix::with_limit)
+    }
+}
+impl Default for Mix {
+    fn default() -> Mix {
+        Mix::new()
+    }
+}
+impl Predictor for Mix {
+    #[inline]
+    fn p(&mut self) -> u32 {
+        self.mixer.add(self.s.stretch(self.order0.p() as i32));
+        self.mixer.add(self.s.stretch(self.order1.p() as i32));
+        self.mixer.add(self.s.stretch(self.order2.p() as i32));
+        self.mixer.add(256); // Bias
+        self.mixer.p(self.cxt).clamp(1, 4095) as u32
+    }
+    #[inline]
+    fn update(&mut self, bit: i32) {
+        self.mixer.update(bit);
+        self.order0.update(bit);
+        self.order1.update(bit);
+        self.order2.update(bit);
+
+        self.cxt = self.cxt * 2 + bit as usize;
+        if self.cxt >= 256 { self.cxt = 1; }
+    }
+    fn model(&self) -> Model {
+        Model::Mix
+    }
+    fn params(&self) -> Vec<u8> {
+        self.limit.to_le_bytes().to_vec()
+    }
+}
+// -----------------------------------------------------------------
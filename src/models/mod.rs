@@ -0,0 +1,240 @@
+mod fpaq0;
+mod fpaq0p;
+mod fpaq0f;
+mod fpaq0f_apm;
+mod order_n;
+mod mix;
+
+use std::ops::RangeInclusive;
+use crate::{
+    error::{ParamError, Result},
+    predictor::Predictor,
+};
+
+pub use crate::statemap::LIMIT;
+pub use self::{
+    fpaq0::Fpaq0,
+    fpaq0p::Fpaq0p,
+    fpaq0f::Fpaq0f,
+    fpaq0f_apm::{Fpaq0fApm, ApmContext, ApmStage, DEFAULT_APM_CHAIN, MATCH_BITS},
+    order_n::OrderN,
+    mix::Mix,
+};
+
+// Model ------------------------------------------------------------
+// Identifies which predictor produced a stream.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Model {
+    Fpaq0,
+    Fpaq0p,
+    Fpaq0f,
+    Fpaq0fApm,
+    Order1,
+    Order2,
+    Mix,
+}
+impl Model {
+    pub const ALL: [Model; 7] = [
+        Model::Fpaq0, 
+        Model::Fpaq0p, 
+        Model::Fpaq0f, 
+        Model::Fpaq0fApm,
+        Model::Order1,
+        Model::Order2,
+        Model::Mix,
+    ];
+
+    pub fn predictor(self) -> Box<dyn Predictor> {
+        match self {
+            Model::Fpaq0     => Box::new(Fpaq0::new()),
+            Model::Fpaq0p    => Box::new(Fpaq0p::new()),
+            Model::Fpaq0f    => Box::new(Fpaq0f::new()),
+            Model::Fpaq0fApm => Box::new(Fpaq0fApm::new()),
+            Model::Order1    => Box::new(OrderN::new(1)),
+            Model::Order2    => Box::new(OrderN::new(2)),
+            Model::Mix       => Box::new(Mix::new()),
+        }
+    }
+    // The predictor with the given adaptation parameters.
+    pub fn tuned_predictor(self, tuning: &Tuning) -> Result<Box<dyn Predictor>> {
+        self.with_tuned(tuning, Boxed)
+    }
+    // The predictor for a stream with the given header params, 
+    // or None if they are not valid for this model.
+    pub fn predictor_with_params(self, params: &[u8]) -> Option<Box<dyn Predictor>> {
+        self.with_params(params, Boxed)
+    }
+    // The model of the original program, for its headerless streams 
+    // (fpaq0f-apm had no match model), or None if there was none.
+    pub fn legacy_predictor(self) -> Option<Box<dyn Predictor>> {
+        self.with_legacy(Boxed)
+    }
+    // As tuned_predictor, running f with the predictor as its own type.
+    pub fn with_tuned<F: WithPredictor>(self, tuning: &Tuning, f: F) -> Result<F::Output> {
+        tuning.check(self)?;
+        let limit = tuning.limit.unwrap_or(LIMIT);
+        Ok(match self {
+            Model::Fpaq0     => f.run(Fpaq0::with_halve_at(tuning.halve_at.unwrap_or(Fpaq0::HALVE_AT))),
+            Model::Fpaq0p    => f.run(Fpaq0p::with_rate(
+                                tuning.shift.unwrap_or(Fpaq0p::SHIFT), tuning.init.unwrap_or(Fpaq0p::INIT))),
+            Model::Fpaq0f    => f.run(Fpaq0f::with_limit(limit)),
+            Model::Fpaq0fApm => f.run(Fpaq0fApm::with_config(Some(MATCH_BITS), 
+                                tuning.apm_chain.as_deref().unwrap_or(&DEFAULT_APM_CHAIN), limit)),
+            Model::Order1    => f.run(OrderN::with_limit(1, limit)),
+            Model::Order2    => f.run(OrderN::with_limit(2, limit)),
+            Model::Mix       => f.run(Mix::with_limit(limit)),
+        })
+    }
+    // As predictor_with_params, running f with the predictor as its own type.
+    pub fn with_params<F: WithPredictor>(self, params: &[u8], f: F) -> Option<F::Output> {
+        match self {
+            Model::Fpaq0     => Fpaq0::from_params(params).map(|p| f.run(p)),
+            Model::Fpaq0p    => Fpaq0p::from_params(params).map(|p| f.run(p)),
+            Model::Fpaq0f    => Fpaq0f::from_params(params).map(|p| f.run(p)),
+            Model::Fpaq0fApm => Fpaq0fApm::from_params(params).map(|p| f.run(p)),
+            Model::Order1    => OrderN::from_params(1, params).map(|p| f.run(p)),
+            Model::Order2    => OrderN::from_params(2, params).map(|p| f.run(p)),
+            Model::Mix       => Mix::from_params(params).map(|p| f.run(p)),
+        }
+    }
+    // As legacy_predictor, running f with the predictor as its own type.
+    pub fn with_legacy<F: WithPredictor>(self, f: F) -> Option<F::Output> {
+        match self {
+            Model::Fpaq0     => Some(f.run(Fpaq0::new())),
+            Model::Fpaq0p    => Some(f.run(Fpaq0p::new())),
+            Model::Fpaq0f    => Some(f.run(Fpaq0f::new())),
+            Model::Fpaq0fApm => Some(f.run(Fpaq0fApm::with_match_bits(None))),
+            _ => None,
+        }
+    }
+    // fpaq0 and fpaq0p split the coder's range with only its high 
+    // 20 bits, as the original programs did, where the other models 
+    // also scale its low 12 bits. High precision coders always do.
+    pub fn coarse_mid(self) -> bool {
+        matches!(self, Model::Fpaq0 | Model::Fpaq0p)
+    }
+    pub fn from_name(name: &str) -> Option<Model> {
+        Model::ALL.into_iter().find(|model| model.name() == name)
+    }
+    pub fn id(self) -> u8 {
+        match self {
+            Model::Fpaq0     => 0,
+            Model::Fpaq0p    => 1,
+            Model::Fpaq0f    => 2,
+            Model::Fpaq0fApm => 3,
+            Model::Order1    => 4,
+            Model::Order2    => 5,
+            Model::Mix       => 6,
+        }
+    }
+    pub fn from_id(id: u8) -> Option<Model> {
+        match id {
+            0 => Some(Model::Fpaq0),
+            1 => Some(Model::Fpaq0p),
+            2 => Some(Model::Fpaq0f),
+            3 => Some(Model::Fpaq0fApm),
+            4 => Some(Model::Order1),
+            5 => Some(Model::Order2),
+            6 => Some(Model::Mix),
+            _ => None,
+        }
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            Model::Fpaq0     => "fpaq0",
+            Model::Fpaq0p    => "fpaq0p",
+            Model::Fpaq0f    => "fpaq0f",
+            Model::Fpaq0fApm => "fpaq0f-apm",
+            Model::Order1    => "order1",
+            Model::Order2    => "order2",
+            Model::Mix       => "mix",
+        }
+    }
+}
+impl std::fmt::Display for Model {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+// ------------------------------------------------------------------
+
+// WithPredictor ----------------------------------------------------
+// Code generic over the predictor, which Model::with_tuned, with_params 
+// and with_legacy run with the model's predictor as its own type. The 
+// coder is then compiled for each model, calling p and update directly 
+// rather than through a Box<dyn Predictor> for every bit.
+pub trait WithPredictor {
+    type Output;
+    fn run<P: Predictor + Clone + Send + Sync + 'static>(self, predictor: P) -> Self::Output;
+}
+struct Boxed;
+impl WithPredictor for Boxed {
+    type Output = Box<dyn Predictor>;
+    fn run<P: Predictor + Clone + Send + Sync + 'static>(self, predictor: P) -> Box<dyn Predictor> {
+        Box::new(predictor)
+    }
+}
+// ------------------------------------------------------------------
+
+// Tuning -----------------------------------------------------------
+// Adaptation parameters chosen at compression time and recorded in 
+// the header params. None keeps the model's default.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Tuning {
+    pub halve_at:   Option<u32>,           // fpaq0 count halving limit
+    pub shift:      Option<u8>,            // fpaq0p update rate
+    pub init:       Option<u16>,           // fpaq0p initial probability
+    pub limit:      Option<u16>,           // StateMap count limit (all other models)
+    pub apm_chain:  Option<Vec<ApmStage>>, // fpaq0f-apm
+}
+impl Tuning {
+    // Fails if a parameter is given that model does not have, 
+    // or is outside of its legal range.
+    pub fn check(&self, model: Model) -> std::result::Result<(), ParamError> {
+        let uses_limit = !matches!(model, Model::Fpaq0 | Model::Fpaq0p);
+        let given = [
+            ("halve-at", self.halve_at.is_some(),  model == Model::Fpaq0),
+            ("shift",    self.shift.is_some(),     model == Model::Fpaq0p),
+            ("init",     self.init.is_some(),      model == Model::Fpaq0p),
+            ("limit",    self.limit.is_some(),     uses_limit),
+            ("apm",      self.apm_chain.is_some(), model == Model::Fpaq0fApm),
+        ];
+        for (name, given, applies) in given {
+            if given && !applies {
+                return Err(ParamError::NotApplicable { name, model });
+            }
+        }
+        check_range("halve-at", self.halve_at, Fpaq0::HALVE_AT_RANGE)?;
+        check_range("shift",    self.shift,    Fpaq0p::SHIFT_RANGE)?;
+        check_range("init",     self.init,     Fpaq0p::INIT_RANGE)?;
+        check_range("limit",    self.limit,    LIMIT_RANGE)?;
+        if self.apm_chain.as_deref().is_some_and(|chain| !fpaq0f_apm::is_valid_apm_chain(chain)) {
+            return Err(ParamError::BadApmChain);
+        }
+        Ok(())
+    }
+}
+fn check_range<T>(name: &'static str, value: Option<T>, range: RangeInclusive<T>) 
+-> std::result::Result<(), ParamError> 
+where T: Copy + PartialOrd + Into<u32> {
+    match value {
+        Some(value) if !range.contains(&value) => Err(ParamError::OutOfRange { 
+            name, 
+            value: value.into(), 
+            min:   (*range.start()).into(), 
+            max:   (*range.end()).into(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+pub const LIMIT_RANGE: RangeInclusive<u16> = 1..=511;
+
+// Params of the StateMap models are the limit (2, LE).
+pub(crate) fn limit_from_params(params: &[u8]) -> Option<u16> {
+    match *params {
+        [a, b] => Some(u16::from_le_bytes([a, b])).filter(|limit| LIMIT_RANGE.contains(limit)),
+        _ => None,
+    }
+}
+// ------------------------------------------------------------------
@@ -0,0 +1,89 @@
+use crate::{
+    logistic::{squash, Stretch},
+    models::{limit_from_params, Model},
+    predictor::Predictor,
+    state_table::next_state,
+    statemap::{StateMap, LIMIT},
+};
+
+const ORDER2_BITS: usize = 22; // Order 2 contexts are hashed into 2^22 slots
+
+// OrderN ----------------------------------------------------------
+// Order 1 or 2 model. The previous 1 or 2 bytes and the partial byte 
+// select a bit history state and a direct prediction. The state is 
+// mapped to a prediction by a StateMap, and the two are averaged in 
+// the stretched domain.
+#[derive(Clone)]
+pub struct OrderN {
+    order:   usize,
+    cxt:     usize,    // Partial byte with a leading 1
+    c4:      usize,    // Last 4 bytes
+    idx:     usize,    // Index of the current context
+    state:   Vec<u8>,  // Bit history state of each context
+    sm:      StateMap, // Maps partial byte and state to a prediction
+    direct:  StateMap, // Maps each context to a prediction
+    s:       Stretch,
+    limit:   u16,
+}
+impl OrderN {
+    pub fn new(order: usize) -> OrderN {
+        OrderN::with_limit(order, LIMIT)
+    }
+    // limit is the StateMap count limit, in LIMIT_RANGE.
+    pub fn with_limit(order: usize, limit: u16) -> OrderN {
+        assert!(order == 1 || order == 2);
+        let bits = if order == 1 { 16 } else { ORDER2_BITS };
+        OrderN {
+            order,
+            cxt:     1,
+            c4:      0,
+            idx:     0,
+            state:   vec![0; 1 << bits],
+            sm:      StateMap::with_limit(65536, limit),
+            direct:  StateMap::with_limit(1 << bits, limit),
+            s:       Stretch::new(),
+            limit,
+        }
+    }
+    pub fn from_params(order: usize, params: &[u8]) -> Option<OrderN> {
+        limit_from_params(params).map(|limit| OrderN::with_limit(order, limit))
+    }
+    #[inline]
+    fn index(&self) -> usize {
+        if self.order == 1 {
+            ((self.c4 & 0xFF) << 8) | self.cxt
+        }
+        else {
+            let key = (((self.c4 & 0xFFFF) << 8) | self.cxt) as u32;
+            (key.wrapping_mul(123456791) >> (32 - ORDER2_BITS)) as usize
+        }
+    }
+}
+impl Predictor for OrderN {
+    #[inline]
+    fn p(&mut self) -> u32 { 
+        let p1 = self.sm.p((self.cxt << 8) | self.state[self.idx] as usize);
+        let p2 = self.direct.p(self.idx);
+        squash((self.s.stretch(p1) + self.s.stretch(p2)) >> 1) as u32
+    } 
+    #[inline]
+    fn update(&mut self, bit: i32) {
+        self.sm.update(bit);
+        self.direct.update(bit);
+        self.state[self.idx] = next_state(self.state[self.idx], bit);
+
+        self.cxt = self.cxt * 2 + bit as usize;
+        if self.cxt >= 256 {
+            self.c4 = ((self.c4 << 8) | (self.cxt - 256)) & 0xFFFFFFFF;
+            self.cxt = 1;
+        }
+        self.idx = self.index();
+    }
+    fn model(&self) -> Model {
+        if self.order == 1 { Model::Order1 } else { Model::Order2 }
+    }
+    fn params(&self) -> Vec<u8> {
+        self.limit.to_le_bytes().to_vec()
+    }
+}
+// -----------------------------------------------------------------
@@ -0,0 +1,60 @@
+use crate::models::Model;
+
+// Predictor --------------------------------------------------------
+/// A model that predicts the next bit of the stream.
+///
+/// `p` returns the probability that the next bit is a 1, scaled to 
+/// 12 bits (0..4096). `update` is then called with the actual bit so 
+/// the model can adapt and move on to the next context.
+///
+/// `p16` is used instead of `p` by the high precision coder, and 
+/// returns the same probability scaled to 16 bits (0..65536). Models 
+/// that compute their prediction more precisely than 12 bits can 
+/// override it to predict bits closer to certain.
+///
+/// Flagged streams code a flag bit before every byte and after the 
+/// last one. The models of the original programs predict it like any 
+/// other bit, in a context of its own, and `flag` returns the value it 
+/// takes when another byte follows (0 for fpaq0 and fpaq0p, 1 for 
+/// fpaq0f and fpaq0f-apm). Length streams code no flags, but still 
+/// update these models with them so that their contexts move on as in 
+/// Flagged streams. Models returning None never see a flag, which the 
+/// coder codes itself with a fixed probability.
+///
+/// `model` and `params` are recorded in the stream header so a stream 
+/// is only ever decoded by the predictor that produced it.
+pub trait Predictor {
+    fn p(&mut self) -> u32;
+    fn p16(&mut self) -> u32 {
+        self.p() << 4
+    }
+    fn update(&mut self, bit: i32);
+    fn flag(&self) -> Option<i32> {
+        None
+    }
+    fn model(&self) -> Model;
+    fn params(&self) -> Vec<u8> {
+        Vec::new()
+    }
+}
+impl<P: Predictor + ?Sized> Predictor for Box<P> {
+    fn p(&mut self) -> u32 {
+        (**self).p()
+    }
+    fn p16(&mut self) -> u32 {
+        (**self).p16()
+    }
+    fn update(&mut self, bit: i32) {
+        (**self).update(bit)
+    }
+    fn flag(&self) -> Option<i32> {
+        (**self).flag()
+    }
+    fn model(&self) -> Model {
+        (**self).model()
+    }
+    fn params(&self) -> Vec<u8> {
+        (**self).params()
+    }
+}
+// ------------------------------------------------------------------
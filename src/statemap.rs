@@ -0,0 +1,52 @@
+#[allow(overflowing_literals)]
+const PR_MSK: i32 = 0xFFFFFC00; // High 22 bit mask
+pub const LIMIT: u16 = 127; // Default rate of adaptation (higher = slower) (1..512)
+
+// StateMap --------------------------------------------------------
+#[derive(Clone)]
+pub struct StateMap {
+    limit:    usize,    // Count limit: controls rate of adaptation
+    cxt:      usize,     
+    cxt_map:  Vec<u32>, // Maps a context to a prediction and a count 
+    rec_t:    Vec<u16>, // Reciprocal table: controls adjustment to cxt_map
+}
+impl StateMap {
+    pub fn new(n: usize) -> StateMap {
+        StateMap::with_limit(n, LIMIT)
+    }
+    pub fn with_limit(n: usize, limit: u16) -> StateMap {
+        assert!((1..512).contains(&limit));
+        StateMap { 
+            limit:    limit as usize,
+            cxt:      0,
+            cxt_map:  vec![1 << 31; n],
+            rec_t:    (0..512).map(|i| 16384/(i+i+3)).collect(),
+        }
+    }
+    #[inline]
+    pub fn p(&mut self, cxt: usize) -> i32 {                   
+        self.cxt = cxt;
+        (self.cxt_map[self.cxt] >> 20) as i32  
+    }
+    // As p, but with 16 bits (0..65536).
+    #[inline]
+    pub fn p16(&mut self, cxt: usize) -> i32 {                   
+        self.cxt = cxt;
+        (self.cxt_map[self.cxt] >> 16) as i32  
+    }
+    #[inline]
+    pub fn update(&mut self, bit: i32) {
+        assert!(bit == 0 || bit == 1);  
+        let count = (self.cxt_map[self.cxt] & 1023) as usize; // Low 10 bits
+        let pr    = (self.cxt_map[self.cxt] >> 10 ) as i32;   // High 22 bits
+
+        if count < self.limit { self.cxt_map[self.cxt] += 1; }
+
+        // Update cxt_map based on prediction error
+        let pr_err = ((bit << 22) - pr) >> 3; // Prediction error
+        let rec_v = self.rec_t[count] as i32; // Reciprocal value
+        self.cxt_map[self.cxt] = 
+        self.cxt_map[self.cxt].wrapping_add(((pr_err * rec_v) & PR_MSK) as u32); 
+    }
+}
+// -----------------------------------------------------------------
@@ -0,0 +1,76 @@
+use std::io::{self, Read, Write};
+use crate::{
+    coder::{Encoder, Decoder},
+    error::Result,
+    header::Precision,
+    predictor::Predictor,
+};
+
+// CompressWriter ---------------------------------------------------
+// Compresses everything written to it into file_out. The length is not 
+// known up front, so the stream is Flagged. The stream is ended by 
+// finish(), or when the writer is dropped (ignoring any errors).
+pub struct CompressWriter<W: Write, P: Predictor> {
+    enc: Option<Encoder<W, P>>,
+}
+impl<W: Write, P: Predictor> CompressWriter<W, P> {
+    pub fn new(predictor: P, file_out: W) -> Result<CompressWriter<W, P>> {
+        CompressWriter::with_precision(predictor, file_out, Precision::Standard)
+    }
+    pub fn with_precision(predictor: P, file_out: W, precision: Precision) -> Result<CompressWriter<W, P>> {
+        Ok(CompressWriter {
+            enc: Some(Encoder::with_precision(predictor, file_out, None, precision)?),
+        })
+    }
+    pub fn finish(mut self) -> Result<W> {
+        let mut enc = self.enc.take().unwrap();
+        enc.flush()?;
+        Ok(enc.into_inner())
+    }
+    pub fn get_ref(&self) -> &W {
+        self.enc.as_ref().unwrap().get_ref()
+    }
+}
+impl<W: Write, P: Predictor> Write for CompressWriter<W, P> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.enc.as_mut().unwrap().encode_bytes(buf)?;
+        Ok(buf.len())
+    }
+    // The last few bytes of the stream are held by the coder 
+    // until finish().
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(self.enc.as_mut().unwrap().flush_output()?)
+    }
+}
+impl<W: Write, P: Predictor> Drop for CompressWriter<W, P> {
+    fn drop(&mut self) {
+        if let Some(enc) = self.enc.as_mut() {
+            let _ = enc.flush();
+        }
+    }
+}
+// ------------------------------------------------------------------
+
+// DecompressReader -------------------------------------------------
+// Decompresses file_in lazily as it is read.
+pub struct DecompressReader<R: Read, P: Predictor> {
+    dec:  Decoder<R, P>,
+}
+impl<R: Read, P: Predictor> DecompressReader<R, P> {
+    // Reads and validates the header.
+    pub fn new(predictor: P, file_in: R) -> Result<DecompressReader<R, P>> {
+        Ok(DecompressReader {
+            dec:  Decoder::new(predictor, file_in)?,
+        })
+    }
+    // file_in may have been read past the end of the stream.
+    pub fn into_inner(self) -> R {
+        self.dec.into_inner()
+    }
+}
+impl<R: Read, P: Predictor> Read for DecompressReader<R, P> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(self.dec.decode_bytes(buf)?)
+    }
+}
+// ------------------------------------------------------------------
@@ -0,0 +1,117 @@
+use std::io;
+use crate::{
+    buffer::Counter,
+    coder::Encoder,
+    error::Result,
+    header::Precision,
+    models::{Fpaq0, Fpaq0p, Model, Tuning, WithPredictor, DEFAULT_APM_CHAIN, LIMIT},
+    predictor::Predictor,
+};
+
+const MAX_PASSES: usize = 3;
+
+// Knob -------------------------------------------------------------
+// An adaptation parameter searched by tune, with the values tried.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Knob {
+    HalveAt,
+    Shift,
+    Limit,
+    ApmRate(usize), // Rate of an fpaq0f-apm APM stage
+}
+impl Knob {
+    pub fn for_model(model: Model) -> Vec<Knob> {
+        match model {
+            Model::Fpaq0     => vec![Knob::HalveAt],
+            Model::Fpaq0p    => vec![Knob::Shift],
+            Model::Fpaq0fApm => [Knob::Limit].into_iter()
+                                .chain((0..DEFAULT_APM_CHAIN.len()).map(Knob::ApmRate))
+                                .collect(),
+            _ => vec![Knob::Limit],
+        }
+    }
+    fn values(self) -> Vec<u32> {
+        match self {
+            Knob::HalveAt    => vec![15, 31, 63, 127, 255, 511, 1023, 4095, 16383, 65534, 262143, 524288],
+            Knob::Shift      => (1..=10).collect(),
+            Knob::Limit      => vec![7, 15, 31, 63, 127, 255, 511],
+            Knob::ApmRate(_) => (3..=10).collect(),
+        }
+    }
+    fn get(self, tuning: &Tuning) -> u32 {
+        match self {
+            Knob::HalveAt    => tuning.halve_at.unwrap_or(Fpaq0::HALVE_AT),
+            Knob::Shift      => tuning.shift.unwrap_or(Fpaq0p::SHIFT) as u32,
+            Knob::Limit      => tuning.limit.unwrap_or(LIMIT) as u32,
+            Knob::ApmRate(i) => tuning.apm_chain.as_deref().unwrap_or(&DEFAULT_APM_CHAIN)[i].rate as u32,
+        }
+    }
+    fn set(self, tuning: &mut Tuning, value: u32) {
+        match self {
+            Knob::HalveAt    => tuning.halve_at = Some(value),
+            Knob::Shift      => tuning.shift    = Some(value as u8),
+            Knob::Limit      => tuning.limit    = Some(value as u16),
+            Knob::ApmRate(i) => {
+                let chain = tuning.apm_chain.get_or_insert_with(|| DEFAULT_APM_CHAIN.to_vec());
+                chain[i].rate = value as u8;
+            }
+        }
+    }
+}
+// ------------------------------------------------------------------
+
+// Search -----------------------------------------------------------
+// Size of data compressed by predictor at precision. The coded bytes 
+// are only counted, not stored.
+pub fn compressed_size<P: Predictor>(predictor: P, data: &[u8], precision: Precision) -> Result<u64> {
+    let mut enc = Encoder::with_precision(predictor, Counter::new(io::sink()), Some(data.len() as u64), precision)?;
+    enc.encode_bytes(data)?;
+    enc.flush()?;
+    Ok(enc.into_inner().count())
+}
+struct CompressedSize<'a> {
+    data:       &'a [u8],
+    precision:  Precision,
+}
+impl WithPredictor for CompressedSize<'_> {
+    type Output = Result<u64>;
+    fn run<P: Predictor + Clone + Send + Sync + 'static>(self, predictor: P) -> Result<u64> {
+        compressed_size(predictor, self.data, self.precision)
+    }
+}
+fn tuned_size(model: Model, tuning: &Tuning, data: &[u8], precision: Precision) -> Result<u64> {
+    model.with_tuned(tuning, CompressedSize { data, precision })?
+}
+
+// Searches the knobs of model one at a time, trying each of its values
+// with the others fixed, until a pass over all of them finds nothing
+// smaller. Calls report with every improvement, and returns the best
+// tuning and the size of data compressed with it at precision.
+pub fn tune<F>(model: Model, data: &[u8], precision: Precision, mut report: F) -> Result<(Tuning, u64)>
+where F: FnMut(&Tuning, u64) {
+    let mut best = Tuning::default();
+    let mut best_size = tuned_size(model, &best, data, precision)?;
+    report(&best, best_size);
+    for _ in 0..MAX_PASSES {
+        let mut improved = false;
+        for knob in Knob::for_model(model) {
+            let current = knob.get(&best);
+            for value in knob.values().into_iter().filter(|&value| value != current) {
+                let mut tuning = best.clone();
+                knob.set(&mut tuning, value);
+                let size = tuned_size(model, &tuning, data, precision)?;
+                if size < best_size {
+                    best = tuning;
+                    best_size = size;
+                    improved = true;
+                    report(&best, best_size);
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+    Ok((best, best_size))
+}
+// ------------------------------------------------------------------
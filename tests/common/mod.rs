@@ -0,0 +1,133 @@
+// Helpers shared by the integration tests. Each test crate uses only
+// some of them.
+#![allow(dead_code)]
+
+use fpaq0::{
+    framed,
+    header::{Header, Mode, Precision},
+    models::Model,
+    compress_stream, decompress_any, Predictor,
+};
+
+// Lines of a log, repeated count times.
+pub fn log_lines(count: usize) -> Vec<u8> {
+    b"2021-06-01 12:00:00 INFO request served in 12ms\n".repeat(count)
+}
+
+// Compresses data in both end of stream modes and at both precisions,
+// checking that every stream records them in its header and decodes 
+// to data with the predictor its header describes.
+pub fn assert_roundtrip<P: Predictor>(new: impl Fn() -> P, data: &[u8], what: &str) {
+    for precision in [Precision::Standard, Precision::High] {
+        for mode in [Mode::Length, Mode::Flagged] {
+            let predictor = new();
+            let model = predictor.model();
+            let len = (mode == Mode::Length).then_some(data.len() as u64);
+            let stream = compress_stream(predictor, &mut &data[..], Vec::new(), len, precision).unwrap();
+            let mut decoded = Vec::new();
+            let header = decompress_any(&stream[..], &mut decoded, None).unwrap_or_else(|e|
+                panic!("{} with {} ({:?}, {:?}): {}", what, model, precision, mode, e));
+            assert_eq!((header.precision, header.mode), (precision, mode), "{} with {}", what, model);
+            assert!(decoded == data, "{} with {} ({:?}, {:?}) decoded wrongly", what, model, precision, mode);
+        }
+    }
+}
+
+// A small seekable container with one block of 1 GiB, whose stream is
+// a Length header claiming the whole block followed by 64 bytes of 
+// 0xFF. fpaq0 decodes them to confident predictions that cost almost 
+// no input, so they decode to data for as long as they are asked to.
+pub fn oversized_block_container() -> Vec<u8> {
+    const BLOCK_SIZE: u32 = 1 << 30;
+    let mut stream = Vec::new();
+    let params = Model::Fpaq0.predictor().params();
+    Header::new(Model::Fpaq0, params, Mode::Length, Precision::Standard, Some(BLOCK_SIZE as u64))
+        .write(&mut stream).unwrap();
+    stream.extend([0xFF; 64]);
+
+    let mut container = framed::MAGIC.to_vec();
+    container.push(framed::SEEKABLE_VERSION);
+    container.extend_from_slice(&BLOCK_SIZE.to_le_bytes());
+    container.extend_from_slice(&(stream.len() as u32).to_le_bytes());
+    container.extend_from_slice(&stream);
+    container.extend_from_slice(&0u32.to_le_bytes());
+    for value in [0, 9, 1, BLOCK_SIZE as u64] {
+        container.extend_from_slice(&value.to_le_bytes());
+    }
+    container.extend_from_slice(&framed::INDEX_MAGIC);
+    container
+}
+
+// Xorshift generator, so every run tests the same inputs.
+pub struct Rng(pub u64);
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+    pub fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+    // Bytes from an alphabet of size symbols, skewed towards the first.
+    pub fn skewed(&mut self, len: usize, symbols: usize) -> Vec<u8> {
+        (0..len).map(|_| {
+            let a = self.below(symbols);
+            let b = self.below(symbols);
+            b'a' + a.min(b) as u8
+        }).collect()
+    }
+    // Runs of random bytes with random lengths.
+    pub fn runs(&mut self, len: usize, max_run: usize) -> Vec<u8> {
+        let mut data = Vec::with_capacity(len + max_run);
+        while data.len() < len {
+            let byte = self.next() as u8;
+            let run = 1 + self.below(max_run);
+            data.extend(std::iter::repeat_n(byte, run));
+        }
+        data.truncate(len);
+        data
+    }
+    // Lines of words, like logs or text.
+    pub fn text(&mut self, len: usize) -> Vec<u8> {
+        const WORDS: [&str; 12] = ["the", "request", "INFO", "served", "in", "ms",
+                                   "GET", "/index.html", "200", "user", "id", "ERROR"];
+        let mut data = Vec::with_capacity(len + 16);
+        while data.len() < len {
+            data.extend_from_slice(WORDS[self.below(WORDS.len())].as_bytes());
+            data.push(if self.below(8) == 0 { b'\n' } else { b' ' });
+        }
+        data.truncate(len);
+        data
+    }
+    // A copy of stream with a few random bytes changed, a random tail,
+    // or cut short.
+    pub fn mutate(&mut self, stream: &[u8]) -> Vec<u8> {
+        let mut stream = stream.to_vec();
+        match self.below(4) {
+            0 => for _ in 0..1 + self.below(4) {
+                let at = self.below(stream.len());
+                stream[at] = self.next() as u8;
+            },
+            1 => {
+                let at = self.below(stream.len());
+                stream.truncate(at);
+                let len = self.below(64);
+                stream.extend(self.bytes(len));
+            }
+            2 => {
+                let at = self.below(stream.len());
+                stream.truncate(at);
+            }
+            _ => {
+                let at = self.below(stream.len().min(32));
+                stream[at] ^= 1 << self.below(8);
+            }
+        }
+        stream
+    }
+}
@@ -0,0 +1,167 @@
+use std::io::Cursor;
+use fpaq0::{
+    error::HeaderError,
+    framed::{compress_framed, compress_seekable, decompress_framed, decompress_range, read_index},
+    header::Precision,
+    models::{Fpaq0f, Model},
+    Error,
+};
+
+mod common;
+
+fn sample() -> Vec<u8> {
+    let mut data = common::log_lines(100);
+    data.extend((0..=255).collect::<Vec<u8>>());
+    data
+}
+
+#[test]
+fn framed_roundtrip() {
+    let data = sample();
+    for (block_size, threads) in [(97, 1), (1000, 3), (4096, 2), (1 << 20, 4)] {
+        let stream = compress_framed(Fpaq0f::new, &mut &data[..], Vec::new(), block_size, threads, Precision::Standard).unwrap();
+        for threads in [1, 5] {
+            let mut decoded = Vec::new();
+            decompress_framed(&mut &stream[..], &mut decoded, threads, None).unwrap();
+            assert_eq!(decoded, data);
+        }
+    }
+    let stream = compress_framed(Fpaq0f::new, &mut &[][..], Vec::new(), 100, 2, Precision::Standard).unwrap();
+    let mut decoded = Vec::new();
+    decompress_framed(&mut &stream[..], &mut decoded, 2, None).unwrap();
+    assert!(decoded.is_empty());
+}
+
+#[test]
+fn blocks_use_their_own_model() {
+    let data = sample();
+    let new_predictor = || Model::Mix.predictor();
+    let stream = compress_framed(new_predictor, &mut &data[..], Vec::new(), 2000, 2, Precision::Standard).unwrap();
+    let mut decoded = Vec::new();
+    decompress_framed(&mut &stream[..], &mut decoded, 2, None).unwrap();
+    assert_eq!(decoded, data);
+}
+
+#[test]
+fn damaged_container_is_an_error() {
+    let data = sample();
+    let stream = compress_framed(Fpaq0f::new, &mut &data[..], Vec::new(), 1000, 2, Precision::Standard).unwrap();
+
+    let result = decompress_framed(&mut &stream[..stream.len() - 10], &mut Vec::new(), 2, None);
+    assert!(matches!(result, Err(Error::Truncated)));
+
+    let mut corrupt = stream.clone();
+    corrupt[0] = b'x';
+    let result = decompress_framed(&mut &corrupt[..], &mut Vec::new(), 2, None);
+    assert!(matches!(result, Err(Error::BadHeader(_))));
+
+    let mut corrupt = stream.clone();
+    let at = corrupt.len() / 2;
+    corrupt[at] ^= 0x10;
+    assert!(decompress_framed(&mut &corrupt[..], &mut Vec::new(), 2, None).is_err());
+}
+
+#[test]
+fn seekable_ranges() {
+    let data = sample();
+    let stream = compress_seekable(Fpaq0f::new, &mut &data[..], Vec::new(), 1000, 2, Precision::Standard).unwrap();
+    let mut file_in = Cursor::new(&stream[..]);
+
+    let (index, len) = read_index(&mut file_in).unwrap();
+    assert_eq!(len, data.len() as u64);
+    assert_eq!(index.len(), data.len().div_ceil(1000));
+    assert!(index.iter().enumerate().all(|(i, &(offset, _))| offset == i as u64 * 1000));
+
+    let n = data.len();
+    for (offset, len) in [(0, n), (0, 0), (999, 2), (1000, 1000), (1500, 2600), (n - 1, 1), (n, 0)] {
+        let part = decompress_range(&mut file_in, offset as u64, len as u64, None).unwrap();
+        assert_eq!(part, &data[offset..offset + len]);
+    }
+    assert!(decompress_range(&mut file_in, n as u64 - 10, 11, None).is_err());
+
+    let mut decoded = Vec::new();
+    decompress_framed(&mut &stream[..], &mut decoded, 3, None).unwrap();
+    assert_eq!(decoded, data);
+}
+
+#[test]
+fn ranges_need_an_index() {
+    let data = sample();
+    let stream = compress_framed(Fpaq0f::new, &mut &data[..], Vec::new(), 1000, 2, Precision::Standard).unwrap();
+    let result = decompress_range(&mut Cursor::new(&stream[..]), 0, 10, None);
+    assert!(matches!(result, Err(Error::BadHeader(HeaderError::NotSeekable))));
+
+    let stream = compress_seekable(Fpaq0f::new, &mut &data[..], Vec::new(), 1000, 2, Precision::Standard).unwrap();
+    let result = decompress_range(&mut Cursor::new(&stream[..stream.len() - 1]), 0, 10, None);
+    assert!(matches!(result, Err(Error::Truncated)));
+}
+
+#[test]
+fn bad_index_is_an_error() {
+    let data = sample();
+    let stream = compress_seekable(Fpaq0f::new, &mut &data[..], Vec::new(), 1000, 2, Precision::Standard).unwrap();
+    let footer = stream.len() - 20;
+    let index = footer - 16 * data.len().div_ceil(1000);
+    let corrupt = |at: usize, value: u64| {
+        let mut corrupt = stream.clone();
+        corrupt[at..at + 8].copy_from_slice(&value.to_le_bytes());
+        corrupt
+    };
+
+    // A length larger than the blocks can hold, checked before any
+    // memory is set aside for the range.
+    let corrupt_len = corrupt(footer + 8, 1 << 62);
+    let result = decompress_range(&mut Cursor::new(&corrupt_len[..]), 0, 1 << 62, None);
+    assert!(matches!(result, Err(Error::BadHeader(HeaderError::BadIndex))));
+
+    // Offsets of blocks that do not follow each other, or of frames 
+    // outside of the container or at a different place.
+    for (at, value) in [(index + 16, 999), (index + 24, 0), (index + 24, stream.len() as u64), (index + 8, 10)] {
+        let corrupt = corrupt(at, value);
+        let result = decompress_range(&mut Cursor::new(&corrupt[..]), 0, data.len() as u64, None);
+        assert!(matches!(result, Err(Error::BadHeader(HeaderError::BadIndex))), "{} {}", at - index, value);
+    }
+}
+
+// Only the bytes up to the end of the range are decoded, not the whole
+// block, and no more than max_output of them.
+#[test]
+fn ranges_decode_only_what_they_cover() {
+    let container = common::oversized_block_container();
+    let (index, len) = read_index(&mut Cursor::new(&container)).unwrap();
+    assert_eq!((index.len(), len), (1, 1 << 30));
+    let part = decompress_range(&mut Cursor::new(&container), 0, 10, None).unwrap();
+    assert_eq!(part.len(), 10);
+    for (offset, len) in [(0, 1 << 30), (1 << 20, 10)] {
+        let result = decompress_range(&mut Cursor::new(&container), offset, len, Some(1 << 16));
+        assert!(matches!(result, Err(Error::OutputLimit { .. })));
+    }
+
+    let data = sample();
+    let stream = compress_seekable(Fpaq0f::new, &mut &data[..], Vec::new(), 1000, 2, Precision::Standard).unwrap();
+    let part = decompress_range(&mut Cursor::new(&stream), 1500, 100, Some(600)).unwrap();
+    assert_eq!(part, &data[1500..1600]);
+    let result = decompress_range(&mut Cursor::new(&stream), 1500, 100, Some(599));
+    assert!(matches!(result, Err(Error::OutputLimit { .. })));
+}
+
+#[test]
+fn output_limit_is_an_error() {
+    let data = sample();
+    let stream = compress_framed(Fpaq0f::new, &mut &data[..], Vec::new(), 1000, 2, Precision::Standard).unwrap();
+    let mut decoded = Vec::new();
+    decompress_framed(&mut &stream[..], &mut decoded, 2, Some(data.len() as u64)).unwrap();
+    assert_eq!(decoded, data);
+    for max in [0, 999, 1000, data.len() as u64 - 1] {
+        let result = decompress_framed(&mut &stream[..], &mut Vec::new(), 2, Some(max));
+        assert!(matches!(result, Err(Error::OutputLimit { .. })));
+    }
+
+    // A block size of 0 or over the largest is rejected before decoding.
+    for block_size in [[0; 4], [0xFF; 4]] {
+        let mut corrupt = stream.clone();
+        corrupt[5..9].copy_from_slice(&block_size);
+        let result = decompress_framed(&mut &corrupt[..], &mut Vec::new(), 2, None);
+        assert!(matches!(result, Err(Error::BadHeader(HeaderError::BadBlockSize(_)))));
+    }
+}
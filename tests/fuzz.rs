@@ -0,0 +1,111 @@
+use std::io::{self, Cursor};
+use fpaq0::{
+    framed::{self, compress_seekable, decompress_range},
+    header::{Mode, Precision},
+    models::{Fpaq0, Model, Tuning},
+    compress_stream, decompress_any, Error, Header, Result,
+};
+
+mod common;
+use common::Rng;
+
+// Most bytes decoded from one input. Garbage decodes to confident
+// predictions that cost almost no input, so without a limit a Length
+// stream with a large length in its header decodes for a long time.
+const MAX_OUTPUT: u64 = 1 << 12;
+
+// Decodes input with the model in its header, up to MAX_OUTPUT bytes.
+fn decode(input: &[u8]) -> Result<Header> {
+    decompress_any(input, &mut io::sink(), Some(MAX_OUTPUT))
+}
+
+fn sample(rng: &mut Rng) -> Vec<u8> {
+    let mut data = common::log_lines(8);
+    let len = rng.below(200);
+    data.extend(rng.bytes(len));
+    data
+}
+
+// Random data behind a valid header of every model, mode and 
+// precision, so that it is decoded rather than rejected by the header.
+#[test]
+fn random_input_is_an_error() {
+    let mut rng = Rng(0x2545F4914F6CDD1D);
+    for model in Model::ALL {
+        let params = model.predictor().params();
+        for mode in [Mode::Flagged, Mode::Length] {
+            for precision in [Precision::Standard, Precision::High] {
+                for _ in 0..10 {
+                    let len = (mode == Mode::Length).then(|| rng.below(200) as u64);
+                    let mut input = Vec::new();
+                    Header::new(model, params.clone(), mode, precision, len).write(&mut input).unwrap();
+                    let body_len = rng.below(100);
+                    input.extend(rng.bytes(body_len));
+                    let result = decode(&input);
+                    assert!(result.is_err() && !matches!(result, Err(Error::BadHeader(_))), 
+                        "{} {:?} {:?}: {:?}", model, mode, precision, result);
+                }
+            }
+        }
+    }
+}
+
+// Each decode stops within MAX_OUTPUT bytes, so a damaged stream that
+// decodes forever fails the limit rather than hangs the test.
+#[test]
+fn damaged_streams_terminate() {
+    let mut rng = Rng(0x9E3779B97F4A7C15);
+    let tunings = [
+        Tuning::default(),
+        Tuning { limit: Some(1), ..Tuning::default() },
+        Tuning { limit: Some(511), ..Tuning::default() },
+    ];
+    for model in Model::ALL {
+        for precision in [Precision::Standard, Precision::High] {
+            for tuning in &tunings {
+                let Ok(predictor) = model.tuned_predictor(tuning) else {
+                    continue;
+                };
+                let data = sample(&mut rng);
+                let len = if rng.below(2) == 0 { Some(data.len() as u64) } else { None };
+                let stream = compress_stream(predictor, &mut &data[..], Vec::new(), len, precision).unwrap();
+                for _ in 0..5 {
+                    let input = rng.mutate(&stream);
+                    let _ = decode(&input);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn damaged_indexes_are_errors() {
+    let mut rng = Rng(0xDEADBEEFCAFEF00D);
+    let data = sample(&mut rng);
+    let stream = compress_seekable(Fpaq0::new, &mut &data[..], Vec::new(), 100, 1, Precision::Standard).unwrap();
+    for _ in 0..300 {
+        let mut input = stream.clone();
+        for _ in 0..1 + rng.below(3) {
+            // Mostly in the index and footer at the end.
+            let at = input.len() - 1 - rng.below(input.len().min(80));
+            input[at] = rng.next() as u8;
+        }
+        if let Ok((index, len)) = framed::read_index(&mut Cursor::new(&input)) {
+            assert!(index.len() <= input.len() / 16);
+            let offset = rng.below(len.min(1000) as usize + 1) as u64;
+            let _ = decompress_range(&mut Cursor::new(&input), offset, len - offset, Some(MAX_OUTPUT));
+        }
+    }
+}
+
+// A tiny container with a 1 GiB block of garbage that decodes for as
+// long as it is asked to.
+#[test]
+fn oversized_blocks_stop_at_the_output_limit() {
+    let input = common::oversized_block_container();
+    let (_, len) = framed::read_index(&mut Cursor::new(&input)).unwrap();
+    let result = decompress_range(&mut Cursor::new(&input), len / 2, len - len / 2, Some(MAX_OUTPUT));
+    assert!(matches!(result, Err(Error::OutputLimit { .. })));
+    let result = framed::decompress_framed(&mut &input[..], &mut io::sink(), 2, Some(MAX_OUTPUT));
+    assert!(matches!(result, Err(Error::OutputLimit { .. })));
+}
@@ -0,0 +1,72 @@
+use fpaq0::{
+    error::HeaderError,
+    header::Header,
+    models::{Fpaq0, Fpaq0p, Fpaq0f, Fpaq0fApm, Model, OrderN},
+    decompress_legacy, Decoder, Encoder, Error, Predictor,
+};
+
+// sample.txt compressed by the original fpaq0.rs, fpaq0p.rs, fpaq0f.rs
+// and fpaq0f-apm.rs programs.
+const SAMPLE: &[u8] = include_bytes!("data/sample.txt");
+const FPAQ0: &[u8] = include_bytes!("data/sample.fpaq0");
+const FPAQ0P: &[u8] = include_bytes!("data/sample.fpaq0p");
+const FPAQ0F: &[u8] = include_bytes!("data/sample.fpaq0f");
+const FPAQ0F_APM: &[u8] = include_bytes!("data/sample.fpaq0f-apm");
+
+// The coded data of a Flagged stream, flushed with a single byte of
+// the coder's range as the original programs did.
+fn coded_data<P: Predictor>(predictor: P, data: &[u8]) -> Vec<u8> {
+    let mut enc = Encoder::new_flagged(predictor, Vec::new()).unwrap();
+    enc.encode_bytes(data).unwrap();
+    enc.flush().unwrap();
+    let stream = enc.into_inner();
+    let mut header = Vec::new();
+    Header::read(&mut &stream[..]).unwrap().write(&mut header).unwrap();
+    stream[header.len()..stream.len() - 4 - 3].to_vec()
+}
+
+#[test]
+fn flagged_streams_match_the_original_programs() {
+    assert_eq!(coded_data(Fpaq0::new(), SAMPLE), FPAQ0);
+    assert_eq!(coded_data(Fpaq0p::new(), SAMPLE), FPAQ0P);
+    assert_eq!(coded_data(Fpaq0f::new(), SAMPLE), FPAQ0F);
+    assert_eq!(coded_data(Fpaq0fApm::with_match_bits(None), SAMPLE), FPAQ0F_APM);
+}
+
+#[test]
+fn reads_streams_of_the_original_programs() {
+    let streams = [
+        (Model::Fpaq0, FPAQ0), 
+        (Model::Fpaq0p, FPAQ0P), 
+        (Model::Fpaq0f, FPAQ0F), 
+        (Model::Fpaq0fApm, FPAQ0F_APM),
+    ];
+    for (model, stream) in streams {
+        let mut decoded = Vec::new();
+        decompress_legacy(model.legacy_predictor().unwrap(), stream, &mut decoded, None).unwrap();
+        assert!(decoded == SAMPLE, "{} decoded wrongly", model);
+    }
+
+    // Bit by bit too, where the decoder reads the flags itself.
+    let mut dec = Decoder::legacy(Fpaq0::new(), FPAQ0).unwrap();
+    for &byte in SAMPLE {
+        let mut decoded = 0;
+        for _ in 0..8 {
+            decoded = (decoded << 1) | dec.decode().unwrap() as u8;
+        }
+        assert_eq!(decoded, byte);
+    }
+    assert_eq!(dec.decode_byte().unwrap(), None);
+}
+
+#[test]
+fn truncated_legacy_stream_is_an_error() {
+    let result = decompress_legacy(Fpaq0f::new(), &FPAQ0F[..FPAQ0F.len() / 2], &mut Vec::new(), None);
+    assert!(matches!(result, Err(Error::Truncated)));
+}
+
+#[test]
+fn legacy_streams_need_a_model_of_the_original_programs() {
+    let result = Decoder::legacy(OrderN::new(1), FPAQ0);
+    assert!(matches!(result, Err(Error::BadHeader(HeaderError::NotLegacy { model: Model::Order1 }))));
+}
@@ -0,0 +1,53 @@
+use fpaq0::models::{Fpaq0, Model, Tuning};
+
+mod common;
+use common::{assert_roundtrip, Rng};
+
+#[test]
+fn fpaq0_count_halving() {
+    // The counts of the first bit's context pass HALVE_AT during the run.
+    let mut data = vec![0; Fpaq0::HALVE_AT as usize + 5000];
+    data.extend([0xFF; 100]);
+    data.extend(Rng(1).bytes(1000));
+    assert_roundtrip(Fpaq0::new, &data, "run past HALVE_AT");
+
+    // The largest limit, where the counts grow largest.
+    let max = *Fpaq0::HALVE_AT_RANGE.end();
+    let mut data = vec![0x55; max as usize + 100];
+    data.extend([0xAA; 100]);
+    assert_roundtrip(|| Fpaq0::with_halve_at(max), &data, "run past the largest limit");
+
+    // Halving after every few bits.
+    let data = Rng(2).text(4000);
+    assert_roundtrip(|| Fpaq0::with_halve_at(2), &data, "text with the smallest limit");
+}
+
+#[test]
+fn statemap_count_saturation() {
+    // Runs long enough for the counts of their contexts to reach the 
+    // largest limit, followed by bytes the saturated contexts predict badly.
+    let mut rng = Rng(3);
+    let mut data = vec![b'a'; 1000];
+    data.extend([b'b'; 1000]);
+    data.extend(rng.skewed(1000, 3));
+    data.extend(rng.bytes(300));
+    for model in [Model::Fpaq0f, Model::Fpaq0fApm, Model::Order1, Model::Order2, Model::Mix] {
+        for limit in [1, 127, 511] {
+            let tuning = Tuning { limit: Some(limit), ..Tuning::default() };
+            assert_roundtrip(|| model.tuned_predictor(&tuning).unwrap(), &data, &format!("limit {}", limit));
+        }
+    }
+}
+
+#[test]
+fn fpaq0p_extreme_rates() {
+    let mut rng = Rng(4);
+    let mut data = vec![0; 1000];
+    data.extend(rng.text(1000));
+    data.extend(rng.bytes(500));
+    for (shift, init) in [(1, 1), (1, 65535), (15, 1), (15, 65535), (5, 32768)] {
+        let tuning = Tuning { shift: Some(shift), init: Some(init), ..Tuning::default() };
+        assert_roundtrip(|| Model::Fpaq0p.tuned_predictor(&tuning).unwrap(), &data,
+                         &format!("shift {}, init {}", shift, init));
+    }
+}
@@ -0,0 +1,355 @@
+use fpaq0::{
+    header::{Mode, Precision},
+    models::{ApmContext, ApmStage, Fpaq0, Fpaq0p, Fpaq0f, Fpaq0fApm, Mix, Model, OrderN, Tuning, DEFAULT_APM_CHAIN, LIMIT},
+    compress, compress_stream, decompress, decompress_any, CompressWriter, Decoder, Encoder, Error, Predictor,
+};
+
+mod common;
+use common::{assert_roundtrip, Rng};
+
+fn sample() -> Vec<u8> {
+    let mut data = b"the quick brown fox jumps over the lazy dog. ".repeat(40);
+    data.extend((0..=255).collect::<Vec<u8>>());
+    data
+}
+
+fn compress_with_mode<P: Predictor>(predictor: P, data: &[u8], mode: Mode) -> Vec<u8> {
+    let mut enc = match mode {
+        Mode::Length  => Encoder::new(predictor, Vec::new(), data.len() as u64),
+        Mode::Flagged => Encoder::new_flagged(predictor, Vec::new()),
+    }.unwrap();
+    for &byte in data {
+        enc.encode_byte(byte).unwrap();
+    }
+    enc.flush().unwrap();
+    enc.into_inner()
+}
+
+fn inputs() -> Vec<(String, Vec<u8>)> {
+    let mut rng = Rng(0x9E3779B97F4A7C15);
+    let mut inputs = vec![
+        ("sample".to_string(), sample()),
+        ("empty".to_string(), vec![]),
+        ("all byte values".to_string(), (0..=255).collect()),
+        ("all byte values, descending".to_string(), (0..=255).rev().collect()),
+        ("run of zeros".to_string(), vec![0; 3000]),
+        ("run of ones".to_string(), vec![0xFF; 3000]),
+        ("alternating".to_string(), [0x00, 0xFF].repeat(1000)),
+        ("random".to_string(), rng.bytes(2000)),
+        ("skewed".to_string(), rng.skewed(2000, 4)),
+        ("runs".to_string(), rng.runs(2000, 300)),
+        ("text".to_string(), rng.text(2000)),
+    ];
+    for byte in [0x00, 0x01, 0x7F, 0x80, 0xFF] {
+        inputs.push((format!("single byte {:02x}", byte), vec![byte]));
+    }
+    for i in 0..4 {
+        let len = rng.below(300);
+        inputs.push((format!("random {} ({} bytes)", i, len), rng.bytes(len)));
+    }
+    inputs
+}
+
+fn roundtrip<P: Predictor>(new: impl Fn() -> P) {
+    for (what, data) in inputs() {
+        assert_roundtrip(&new, &data, &what);
+    }
+}
+
+#[test]
+fn fpaq0_roundtrip() {
+    roundtrip(Fpaq0::new);
+}
+
+#[test]
+fn fpaq0p_roundtrip() {
+    roundtrip(Fpaq0p::new);
+}
+
+#[test]
+fn fpaq0f_roundtrip() {
+    roundtrip(Fpaq0f::new);
+}
+
+#[test]
+fn fpaq0f_apm_roundtrip() {
+    roundtrip(Fpaq0fApm::new);
+}
+
+#[test]
+fn fpaq0f_apm_without_match_model_roundtrip() {
+    roundtrip(|| Fpaq0fApm::with_match_bits(None));
+}
+
+#[test]
+fn match_model_compresses_repeats_better() {
+    let mut data: Vec<u8> = (0..4000u32).map(|i| (i.wrapping_mul(2654435761) >> 24) as u8).collect();
+    data.extend_from_within(..);
+    let with = compress(Fpaq0fApm::new(), &data).len();
+    let without = compress(Fpaq0fApm::with_match_bits(None), &data).len();
+    assert!(with < without * 3 / 4);
+}
+
+#[test]
+fn decompress_any_uses_header_params() {
+    let data = sample();
+    let stream = compress(Fpaq0fApm::with_match_bits(Some(16)), &data);
+    let mut decoded = Vec::new();
+    let header = decompress_any(&stream[..], &mut decoded, None).unwrap();
+    assert_eq!(header.params[0], 16);
+    assert_eq!(decoded, data);
+    assert!(matches!(decompress(Fpaq0fApm::new(), &stream), Err(Error::BadHeader(_))));
+}
+
+#[test]
+fn apm_chain_is_stored_in_the_header() {
+    const CHAIN: [ApmStage; 3] = [
+        ApmStage::new(ApmContext::Order1, 16, 6, 4),
+        ApmStage::new(ApmContext::Hash16, 12, 7, 2).parallel(),
+        ApmStage::new(ApmContext::Hash24, 18, 5, 1),
+    ];
+    let chain = CHAIN;
+    assert_roundtrip(|| Fpaq0fApm::with_apm_chain(&CHAIN), &sample(), "sample");
+
+    let data = sample();
+    let stream = compress(Fpaq0fApm::with_apm_chain(&chain), &data);
+    let mut decoded = Vec::new();
+    decompress_any(&stream[..], &mut decoded, None).unwrap();
+    assert_eq!(decoded, data);
+    assert!(matches!(decompress(Fpaq0fApm::new(), &stream), Err(Error::BadHeader(_))));
+
+    let params = Fpaq0fApm::with_apm_chain(&chain).params();
+    assert_eq!(Fpaq0fApm::from_params(&params).unwrap().apm_chain(), chain);
+    assert_eq!(Fpaq0fApm::from_params(&Fpaq0fApm::new().params()).unwrap().apm_chain(), DEFAULT_APM_CHAIN);
+}
+
+#[test]
+fn invalid_apm_chain_is_an_error() {
+    let mut params = Fpaq0fApm::new().params();
+    params[3] = 7; // Order0 APM with too small a table
+    assert!(Fpaq0fApm::from_params(&params).is_none());
+    assert!("order0:8:5:4:p".parse::<ApmStage>().is_ok());
+    assert!("order1:8:5:4".parse::<ApmStage>().is_err());
+    assert!("order0:8:5".parse::<ApmStage>().is_err());
+    let huge = [ApmStage::new(ApmContext::Hash24, 20, 7, 2); 3]; // Too many contexts in all
+    let tuning = |n| Tuning { apm_chain: Some(huge[..n].to_vec()), ..Tuning::default() };
+    assert!(tuning(2).check(Model::Fpaq0fApm).is_ok());
+    assert!(tuning(3).check(Model::Fpaq0fApm).is_err());
+
+    let stream = compress(Fpaq0fApm::new(), b"abc");
+    let mut corrupt = stream.clone();
+    corrupt[10] = 0xFF; // Context id of the first stage
+    let result = decompress_any(&corrupt[..], &mut Vec::new(), None);
+    assert!(matches!(result, Err(Error::BadHeader(_))));
+}
+
+#[test]
+fn tuned_models_roundtrip() {
+    let data = sample();
+    assert_roundtrip(|| Fpaq0::with_halve_at(30), &data, "sample");
+    assert_roundtrip(|| Fpaq0p::with_rate(2, 1000), &data, "sample");
+    assert_roundtrip(|| Fpaq0f::with_limit(1), &data, "sample");
+    assert_roundtrip(|| OrderN::with_limit(2, 511), &data, "sample");
+
+    let tunings = [
+        (Model::Fpaq0,     Tuning { halve_at: Some(1 << 19), ..Tuning::default() }),
+        (Model::Fpaq0p,    Tuning { shift: Some(15), init: Some(1), ..Tuning::default() }),
+        (Model::Fpaq0fApm, Tuning { limit: Some(20), ..Tuning::default() }),
+        (Model::Mix,       Tuning { limit: Some(300), ..Tuning::default() }),
+    ];
+    for (model, tuning) in tunings {
+        let stream = compress(model.tuned_predictor(&tuning).unwrap(), &data);
+        let mut decoded = Vec::new();
+        decompress_any(&stream[..], &mut decoded, None).unwrap();
+        assert_eq!(decoded, data);
+        assert!(matches!(decompress(model.predictor(), &stream), Err(Error::BadHeader(_))));
+    }
+}
+
+#[test]
+fn invalid_tuning_is_an_error() {
+    let out_of_range = [
+        (Model::Fpaq0,  Tuning { halve_at: Some(1), ..Tuning::default() }),
+        (Model::Fpaq0p, Tuning { shift: Some(16), ..Tuning::default() }),
+        (Model::Fpaq0p, Tuning { init: Some(0), ..Tuning::default() }),
+        (Model::Order1, Tuning { limit: Some(512), ..Tuning::default() }),
+        (Model::Fpaq0,  Tuning { limit: Some(100), ..Tuning::default() }),
+    ];
+    for (model, tuning) in out_of_range {
+        assert!(matches!(model.tuned_predictor(&tuning), Err(Error::InvalidParam(_))));
+    }
+    assert!(Fpaq0::from_params(&1u32.to_le_bytes()).is_none());
+    assert!(Fpaq0p::from_params(&[0, 0, 128]).is_none());
+    assert!(Fpaq0f::from_params(&[0, 2]).is_none());
+
+    let mut stream = compress(Fpaq0f::new(), b"abc");
+    stream[10] = 2; // Limit 512
+    let result = decompress_any(&stream[..], &mut Vec::new(), None);
+    assert!(matches!(result, Err(Error::BadHeader(_))));
+}
+
+#[test]
+fn order1_roundtrip() {
+    roundtrip(|| OrderN::new(1));
+}
+
+#[test]
+fn order2_roundtrip() {
+    roundtrip(|| OrderN::new(2));
+}
+
+#[test]
+fn mix_roundtrip() {
+    roundtrip(Mix::new);
+}
+
+#[test]
+fn higher_orders_compress_text_better() {
+    let data = sample();
+    let order0 = compress(Fpaq0f::new(), &data).len();
+    let order1 = compress(OrderN::new(1), &data).len();
+    let order2 = compress(OrderN::new(2), &data).len();
+    assert!(order1 < order0);
+    assert!(order2 < order1);
+    assert!(compress(Mix::new(), &data).len() < order2);
+}
+
+// Mix codes no flags, so its order 0 input is an fpaq0f that implies 
+// them. It must predict every bit as fpaq0f given a flag before each 
+// byte does, rather than drift off the byte boundaries.
+#[test]
+fn mix_order0_is_aligned_to_bytes() {
+    let mut flagged = Fpaq0f::new();
+    let mut implied = Fpaq0f::without_flags(LIMIT);
+    assert_eq!(implied.flag(), None);
+    for &byte in &sample() {
+        flagged.p();
+        flagged.update(1);
+        for i in (0..=7).rev() {
+            let bit = ((byte >> i) & 1) as i32;
+            assert_eq!(implied.p(), flagged.p());
+            implied.update(bit);
+            flagged.update(bit);
+        }
+    }
+}
+
+#[test]
+fn bulk_coding_matches_bitwise_coding() {
+    let data = sample();
+    for model in Model::ALL {
+        let mut enc = Encoder::new(model.predictor(), Vec::new(), data.len() as u64).unwrap();
+        for &byte in &data {
+            for i in (0..=7).rev() {
+                enc.encode(((byte >> i) & 1).into()).unwrap();
+            }
+        }
+        enc.flush().unwrap();
+        let stream = enc.into_inner();
+        let mut enc = Encoder::new(model.predictor(), Vec::new(), data.len() as u64).unwrap();
+        enc.encode_bytes(&data).unwrap();
+        enc.flush().unwrap();
+        assert_eq!(enc.into_inner(), stream);
+
+        let mut dec = Decoder::new(model.predictor(), &stream[..]).unwrap();
+        let mut decoded = vec![0; data.len() + 10];
+        let len = dec.decode_bytes(&mut decoded).unwrap();
+        assert_eq!(&decoded[..len], data);
+    }
+}
+
+#[test]
+fn high_precision_roundtrip() {
+    let data = sample();
+    let mut writer = CompressWriter::with_precision(Fpaq0fApm::new(), Vec::new(), Precision::High).unwrap();
+    std::io::Write::write_all(&mut writer, &data).unwrap();
+    let stream = writer.finish().unwrap();
+    assert_eq!(decompress(Fpaq0fApm::new(), &stream).unwrap(), data);
+
+    let mut high = compress_stream(Fpaq0f::new(), &mut &data[..], Vec::new(), Some(data.len() as u64), Precision::High).unwrap();
+    high[7] = 2; // Unknown precision
+    assert!(matches!(decompress(Fpaq0f::new(), &high), Err(Error::BadHeader(_))));
+}
+
+#[test]
+fn length_mode_is_smaller() {
+    let data = sample();
+    let length = compress_with_mode(Fpaq0f::new(), &data, Mode::Length);
+    let flagged = compress_with_mode(Fpaq0f::new(), &data, Mode::Flagged);
+    assert!(length.len() <= flagged.len());
+}
+
+#[test]
+fn truncated_header_is_an_error() {
+    let stream = compress(Fpaq0::new(), b"abc");
+    let result = Decoder::new(Fpaq0::new(), &stream[..10]);
+    assert!(matches!(result, Err(Error::Truncated)));
+}
+
+#[test]
+fn wrong_model_is_an_error() {
+    let stream = compress(Fpaq0f::new(), b"abc");
+    assert!(matches!(decompress(Fpaq0p::new(), &stream), Err(Error::BadHeader(_))));
+}
+
+#[test]
+fn corruption_is_an_error() {
+    let data = sample();
+    let stream = compress(Fpaq0f::new(), &data);
+    for at in [stream.len() / 2, stream.len() - 1] {
+        let mut corrupt = stream.clone();
+        corrupt[at] ^= 0x10;
+        assert!(matches!(decompress(Fpaq0f::new(), &corrupt), Err(Error::ChecksumMismatch { .. })));
+    }
+}
+
+#[test]
+fn truncated_stream_is_an_error() {
+    let data = sample();
+    for mode in [Mode::Length, Mode::Flagged] {
+        let stream = compress_with_mode(Fpaq0f::new(), &data, mode);
+        for len in [stream.len() / 2, stream.len() - 2] {
+            let mut dec = Decoder::new(Fpaq0f::new(), &stream[..len]).unwrap();
+            let result = loop {
+                match dec.decode_byte() {
+                    Ok(Some(_)) => {},
+                    Ok(None) => break Ok(()),
+                    Err(e) => break Err(e),
+                }
+            };
+            assert!(matches!(result, Err(Error::Truncated)));
+        }
+    }
+}
+
+#[test]
+fn output_limit_is_an_error() {
+    let data = sample();
+    let len = data.len() as u64;
+    for mode in [Mode::Length, Mode::Flagged] {
+        let stream = compress_with_mode(Fpaq0f::new(), &data, mode);
+        let mut decoded = Vec::new();
+        decompress_any(&stream[..], &mut decoded, Some(len)).unwrap();
+        assert_eq!(decoded, data);
+
+        let mut decoded = Vec::new();
+        let result = decompress_any(&stream[..], &mut decoded, Some(len - 1));
+        assert!(matches!(result, Err(Error::OutputLimit { limit }) if limit == len - 1));
+        // The length in the header is checked before decoding.
+        if mode == Mode::Length {
+            assert!(decoded.is_empty());
+        }
+    }
+}
+
+#[test]
+fn wrong_length_is_an_error() {
+    for count in [2, 4] {
+        let mut enc = Encoder::new(Fpaq0f::new(), Vec::new(), 3).unwrap();
+        enc.encode_bytes(&b"abcd"[..count]).unwrap();
+        let result = enc.flush();
+        assert!(matches!(result, Err(Error::LengthMismatch { expected: 3, found }) if found == count as u64));
+    }
+    let result = compress_stream(Fpaq0f::new(), &mut &b"abc"[..], Vec::new(), Some(4), Precision::Standard);
+    assert!(matches!(result, Err(Error::LengthMismatch { expected: 4, found: 3 })));
+}
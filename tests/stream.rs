@@ -0,0 +1,41 @@
+use std::io::{self, Read, Write};
+use fpaq0::{
+    models::{Fpaq0p, Fpaq0fApm},
+    CompressWriter, DecompressReader,
+};
+
+mod common;
+
+#[test]
+fn copy_roundtrip() {
+    let data = common::log_lines(100);
+    let mut writer = CompressWriter::new(Fpaq0fApm::new(), Vec::new()).unwrap();
+    io::copy(&mut &data[..], &mut writer).unwrap();
+    let stream = writer.finish().unwrap();
+
+    let mut reader = DecompressReader::new(Fpaq0fApm::new(), &stream[..]).unwrap();
+    let mut decoded = Vec::new();
+    io::copy(&mut reader, &mut decoded).unwrap();
+    assert_eq!(decoded, data);
+}
+
+#[test]
+fn drop_finishes_stream() {
+    let data = common::log_lines(100);
+    let mut stream = Vec::new();
+    {
+        let mut writer = CompressWriter::new(Fpaq0p::new(), &mut stream).unwrap();
+        for line in data.chunks(7) {
+            writer.write_all(line).unwrap();
+        }
+    }
+    let mut reader = DecompressReader::new(Fpaq0p::new(), &stream[..]).unwrap();
+    let mut decoded = Vec::new();
+    let mut buf = [0; 5];
+    loop {
+        let n = reader.read(&mut buf).unwrap();
+        if n == 0 { break; }
+        decoded.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(decoded, data);
+}
@@ -0,0 +1,36 @@
+use fpaq0::{
+    header::Precision,
+    models::{Fpaq0p, Model},
+    tune::{compressed_size, tune},
+    compress_stream, Predictor,
+};
+
+mod common;
+
+fn compress_with_precision<P: Predictor>(predictor: P, data: &[u8], precision: Precision) -> Vec<u8> {
+    compress_stream(predictor, &mut &data[..], Vec::new(), Some(data.len() as u64), precision).unwrap()
+}
+
+#[test]
+fn compressed_size_counts_the_stream() {
+    let data = common::log_lines(50);
+    for precision in [Precision::Standard, Precision::High] {
+        let size = compressed_size(Fpaq0p::new(), &data, precision).unwrap();
+        assert_eq!(size, compress_with_precision(Fpaq0p::new(), &data, precision).len() as u64);
+    }
+}
+
+#[test]
+fn tune_is_no_worse_than_the_defaults() {
+    let data = common::log_lines(50);
+    for precision in [Precision::Standard, Precision::High] {
+        for model in [Model::Fpaq0, Model::Fpaq0p, Model::Order1] {
+            let mut reports = 0;
+            let (tuning, size) = tune(model, &data, precision, |_, _| reports += 1).unwrap();
+            assert!(reports >= 1);
+            assert!(size <= compressed_size(model.predictor(), &data, precision).unwrap());
+            let stream = compress_with_precision(model.tuned_predictor(&tuning).unwrap(), &data, precision);
+            assert_eq!(size, stream.len() as u64);
+        }
+    }
+}